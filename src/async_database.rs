@@ -0,0 +1,141 @@
+use std::{io, net::IpAddr, path::Path, sync::Arc};
+
+use positioned_io::RandomAccessFile;
+
+use crate::{Columns, Database, Error, Row, Source};
+
+/// An IP2Proxy BIN database for use in async code.
+///
+/// Wraps a [`Database`] and runs lookups on the blocking thread pool of the
+/// [tokio](https://tokio.rs) runtime, so that reads on a cold page cache do
+/// not stall the executor.
+///
+/// Cloning is cheap and shares the underlying database.
+///
+/// # Example
+///
+/// ```
+/// use ip2proxy::{AsyncDatabase, Columns};
+///
+/// # let rt = tokio::runtime::Builder::new_current_thread().build()?;
+/// # rt.block_on(async {
+/// let db = AsyncDatabase::open("data/IP2PROXY-IP-PROXYTYPE-COUNTRY-REGION-CITY-ISP.SAMPLE.BIN").await?;
+///
+/// let row = db.query("1.0.0.1".parse()?, Columns::PROXY_TYPE).await?;
+/// assert_eq!(row.and_then(|r| r.proxy_type), Some(String::from("DCH")));
+/// # Ok::<_, Box<dyn std::error::Error>>(())
+/// # })?;
+/// # Ok::<_, Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+pub struct AsyncDatabase<R = RandomAccessFile> {
+    inner: Arc<Database<R>>,
+}
+
+impl<R> Clone for AsyncDatabase<R> {
+    fn clone(&self) -> Self {
+        AsyncDatabase {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl AsyncDatabase {
+    /// Open a database file on the blocking thread pool.
+    ///
+    /// # Errors
+    ///
+    /// See [`Database::open()`].
+    pub async fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref().to_owned();
+        spawn_blocking(move || Database::open(path))
+            .await
+            .map(AsyncDatabase::from)
+    }
+}
+
+impl<R: Source + Send + Sync + 'static> AsyncDatabase<R> {
+    /// Look up information for an IP address on the blocking thread pool.
+    ///
+    /// See [`Database::query()`].
+    ///
+    /// # Errors
+    ///
+    /// * Error while reading from the source.
+    /// * Invalid row or string data.
+    pub async fn query(&self, addr: IpAddr, query: Columns) -> Result<Option<Row>, Error> {
+        let inner = Arc::clone(&self.inner);
+        spawn_blocking(move || inner.query(addr, query)).await
+    }
+}
+
+impl<R> AsyncDatabase<R> {
+    /// Get the underlying database, for example to access header
+    /// information like [`Database::package_version()`].
+    pub fn database(&self) -> &Database<R> {
+        &self.inner
+    }
+}
+
+impl<R> From<Database<R>> for AsyncDatabase<R> {
+    fn from(db: Database<R>) -> AsyncDatabase<R> {
+        AsyncDatabase {
+            inner: Arc::new(db),
+        }
+    }
+}
+
+async fn spawn_blocking<T, F>(f: F) -> Result<T, Error>
+where
+    F: FnOnce() -> Result<T, Error> + Send + 'static,
+    T: Send + 'static,
+{
+    match tokio::task::spawn_blocking(f).await {
+        Ok(res) => res,
+        Err(err) if err.is_panic() => std::panic::resume_unwind(err.into_panic()),
+        Err(err) => Err(Error::Io(io::Error::other(err))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::task::JoinSet;
+
+    use super::*;
+    use crate::tests::{SAMPLE, SAMPLE_COUNTRIES};
+
+    #[tokio::test]
+    async fn test_concurrent_queries() {
+        let db = AsyncDatabase::open(SAMPLE).await.unwrap();
+
+        // Clones share the database, and run lookups concurrently.
+        let mut tasks = JoinSet::new();
+        for _ in 0..10 {
+            for (addr, country) in SAMPLE_COUNTRIES {
+                let clone = db.clone();
+                assert!(std::ptr::eq(clone.database(), db.database()));
+                tasks.spawn(async move {
+                    let row = clone
+                        .query(addr.parse().unwrap(), Columns::COUNTRY_SHORT)
+                        .await;
+                    (row.unwrap().unwrap().country_short, country)
+                });
+            }
+        }
+        let mut done = 0;
+        while let Some(res) = tasks.join_next().await {
+            let (actual, expected) = res.unwrap();
+            assert_eq!(actual.as_deref(), Some(expected));
+            done += 1;
+        }
+        assert_eq!(done, 10 * SAMPLE_COUNTRIES.len());
+    }
+
+    #[tokio::test]
+    async fn test_open_missing() {
+        assert!(matches!(
+            AsyncDatabase::open("data/missing.BIN").await,
+            Err(Error::Io(err)) if err.kind() == io::ErrorKind::NotFound
+        ));
+    }
+}
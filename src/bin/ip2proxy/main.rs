@@ -0,0 +1,600 @@
+//! Command-line tool to query IP2Proxy BIN data files.
+
+use std::{
+    error::Error,
+    fs::File,
+    io,
+    io::{BufRead, BufReader, BufWriter, Write},
+    net::IpAddr,
+    path::PathBuf,
+    process::ExitCode,
+    time::SystemTime,
+};
+
+use clap::{Parser, Subcommand, ValueEnum};
+use ip2proxy::{
+    ChangeCounts, ChangeKind, Columns, Database, DatabaseBuilder, DiffSummary, Family, MmdbLayout,
+    Row,
+};
+
+#[derive(Debug, Parser)]
+#[command(version, about)]
+struct Cli {
+    /// Output format.
+    #[arg(short, long, value_enum, default_value_t = Format::Table, global = true)]
+    format: Format,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Look up IP addresses. Reads addresses from stdin, one per line, if
+    /// none are given.
+    Lookup {
+        /// Path to the BIN database file.
+        database: PathBuf,
+
+        /// IP addresses to look up.
+        addrs: Vec<IpAddr>,
+
+        /// Comma-separated list of columns to retrieve, like
+        /// `proxy_type,country_short`. Defaults to all supported columns.
+        #[arg(short, long, value_parser = parse_columns)]
+        columns: Option<Columns>,
+    },
+    /// Print information about the database.
+    Info {
+        /// Path to the BIN database file.
+        database: PathBuf,
+    },
+    /// Check the structure of the database. Exits with failure if any
+    /// problems are found.
+    Verify {
+        /// Path to the BIN database file.
+        database: PathBuf,
+    },
+    /// Convert an IP2Proxy CSV distribution (PX1 - PX11) to a BIN database
+    /// file.
+    Import {
+        /// Path to the CSV file.
+        csv: PathBuf,
+
+        /// Path of the BIN database file to write.
+        output: PathBuf,
+
+        /// Database version as `YY.M.D`. Defaults to the current date.
+        #[arg(long, value_parser = parse_date)]
+        date: Option<(u8, u8, u8)>,
+    },
+    /// Export a BIN database file to stdout, as CSV in the layout of the
+    /// official distributions or as JSON.
+    Export {
+        /// Path to the BIN database file.
+        database: PathBuf,
+
+        /// Address families to export.
+        #[arg(long, value_enum, default_value_t = ExportFamily::Both)]
+        family: ExportFamily,
+
+        /// Comma-separated list of columns to export, like
+        /// `proxy_type,country_short`. Defaults to all supported columns.
+        #[arg(short, long, value_parser = parse_columns)]
+        columns: Option<Columns>,
+    },
+    /// Convert a BIN database file to a MaxMind DB file, with each column
+    /// at the top level of the records, keyed by its name.
+    ExportMmdb {
+        /// Path to the BIN database file.
+        database: PathBuf,
+
+        /// Path of the MaxMind DB file to write.
+        output: PathBuf,
+
+        /// Comma-separated list of columns to export, like
+        /// `proxy_type,country_short`. Defaults to all supported columns.
+        #[arg(short, long, value_parser = parse_columns)]
+        columns: Option<Columns>,
+    },
+    /// Show ranges that were added, removed or changed between two
+    /// releases of a database.
+    Diff {
+        /// Path to the old BIN database file.
+        old: PathBuf,
+
+        /// Path to the new BIN database file.
+        new: PathBuf,
+
+        /// Print only counts by proxy type and country.
+        #[arg(long)]
+        summary: bool,
+    },
+}
+
+#[derive(Debug, Copy, Clone, ValueEnum)]
+enum ExportFamily {
+    /// IPv4 table only.
+    Ipv4,
+    /// IPv6 table only.
+    Ipv6,
+    /// Both tables.
+    Both,
+}
+
+impl From<ExportFamily> for Family {
+    fn from(family: ExportFamily) -> Family {
+        match family {
+            ExportFamily::Ipv4 => Family::Ipv4,
+            ExportFamily::Ipv6 => Family::Ipv6,
+            ExportFamily::Both => Family::Both,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, ValueEnum)]
+enum Format {
+    /// Aligned plain text.
+    Table,
+    /// JSON, one object per line.
+    Json,
+    /// CSV with header line.
+    Csv,
+}
+
+fn parse_columns(s: &str) -> Result<Columns, String> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .try_fold(Columns::empty(), |acc, name| {
+            Columns::from_name(&name.to_ascii_uppercase())
+                .map(|column| acc | column)
+                .ok_or_else(|| format!("unknown column: {name}"))
+        })
+}
+
+fn parse_date(s: &str) -> Result<(u8, u8, u8), String> {
+    let mut parts = s.split('.').map(str::parse);
+    match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(Ok(year)), Some(Ok(month)), Some(Ok(day)), None) => Ok((year, month, day)),
+        _ => Err(String::from("expected date as YY.M.D")),
+    }
+}
+
+fn column_names(columns: Columns) -> impl Iterator<Item = (String, Columns)> {
+    columns
+        .iter_names()
+        .map(|(name, column)| (name.to_ascii_lowercase(), column))
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("ip2proxy: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+    match cli.command {
+        Command::Lookup {
+            database,
+            addrs,
+            columns,
+        } => {
+            let db = Database::open(database)?;
+            let columns = columns.unwrap_or_else(Columns::all) & db.columns();
+            let mut output = LookupOutput::new(cli.format, columns)?;
+            if addrs.is_empty() {
+                for line in io::stdin().lock().lines() {
+                    let line = line?;
+                    let line = line.trim();
+                    if line.is_empty() {
+                        continue;
+                    }
+                    let addr = line
+                        .parse()
+                        .map_err(|err| format!("invalid ip address {line:?}: {err}"))?;
+                    output.write(addr, db.query(addr, columns)?.as_ref())?;
+                }
+            } else {
+                let rows = db.query_many(&addrs, columns)?;
+                for (addr, row) in addrs.into_iter().zip(rows) {
+                    output.write(addr, row.as_ref())?;
+                }
+            }
+            output.finish()
+        }
+        Command::Info { database } => {
+            let db = Database::open(database)?;
+            info(cli.format, &db)
+        }
+        Command::Verify { database } => {
+            let db = Database::open(database)?;
+            let report = db.validate()?;
+            let problems: Vec<String> = report.problems().iter().map(ToString::to_string).collect();
+            match cli.format {
+                Format::Table => {
+                    let mut out = io::stdout().lock();
+                    for problem in &problems {
+                        writeln!(out, "{problem}")?;
+                    }
+                    out.flush()?;
+                }
+                Format::Json => {
+                    println!(
+                        "{}",
+                        serde_json::json!({ "ok": report.is_ok(), "problems": problems })
+                    );
+                }
+                Format::Csv => {
+                    let mut writer = csv::Writer::from_writer(io::stdout().lock());
+                    writer.write_record(["problem"])?;
+                    for problem in &problems {
+                        writer.write_record([problem])?;
+                    }
+                    writer.flush()?;
+                }
+            }
+            if report.is_ok() {
+                Ok(())
+            } else {
+                Err(format!("found {} problems", problems.len()).into())
+            }
+        }
+        Command::Import { csv, output, date } => {
+            let mut builder = DatabaseBuilder::from_csv(BufReader::new(File::open(csv)?))?;
+            match date {
+                Some((year, month, day)) => builder.date(year, month, day),
+                None => builder.date_of(SystemTime::now()),
+            };
+            let mut writer = BufWriter::new(File::create(output)?);
+            builder.write(&mut writer)?;
+            writer
+                .into_inner()
+                .map_err(|err| err.into_error())?
+                .sync_all()?;
+            Ok(())
+        }
+        Command::Export {
+            database,
+            family,
+            columns,
+        } => {
+            let db = Database::open(database)?;
+            let columns = columns.unwrap_or_else(Columns::all);
+            let out = BufWriter::new(io::stdout().lock());
+            match cli.format {
+                Format::Table => Err("export supports --format csv or json")?,
+                Format::Json => db.export_json_lines(out, columns, family.into())?,
+                Format::Csv => db.export_csv(out, columns, family.into())?,
+            }
+            Ok(())
+        }
+        Command::ExportMmdb {
+            database,
+            output,
+            columns,
+        } => {
+            let db = Database::open(database)?;
+            let columns = columns.unwrap_or_else(Columns::all);
+            let layout = column_names(columns).fold(MmdbLayout::new(), |layout, (name, column)| {
+                layout.field(column, name)
+            });
+            let mut writer = BufWriter::new(File::create(output)?);
+            db.export_mmdb(&mut writer, &layout)?;
+            writer
+                .into_inner()
+                .map_err(|err| err.into_error())?
+                .sync_all()?;
+            Ok(())
+        }
+        Command::Diff { old, new, summary } => {
+            let old = Database::open(old)?;
+            let new = Database::open(new)?;
+            diff(cli.format, &old, &new, summary)
+        }
+    }
+}
+
+enum LookupOutput {
+    Table {
+        columns: Columns,
+        lines: Vec<Vec<String>>,
+    },
+    Json {
+        out: io::StdoutLock<'static>,
+    },
+    Csv {
+        columns: Columns,
+        writer: Box<csv::Writer<io::StdoutLock<'static>>>,
+    },
+}
+
+impl LookupOutput {
+    fn new(format: Format, columns: Columns) -> Result<LookupOutput, Box<dyn Error>> {
+        let header = || {
+            let mut header = vec![String::from("ip")];
+            header.extend(column_names(columns).map(|(name, _)| name));
+            header
+        };
+        Ok(match format {
+            Format::Table => LookupOutput::Table {
+                columns,
+                lines: vec![header()],
+            },
+            Format::Json => LookupOutput::Json {
+                out: io::stdout().lock(),
+            },
+            Format::Csv => {
+                let mut writer = Box::new(csv::Writer::from_writer(io::stdout().lock()));
+                writer.write_record(header())?;
+                LookupOutput::Csv { columns, writer }
+            }
+        })
+    }
+
+    fn write(&mut self, addr: IpAddr, row: Option<&Row>) -> Result<(), Box<dyn Error>> {
+        let record = |columns: Columns| {
+            let mut record = vec![addr.to_string()];
+            record.extend(column_names(columns).map(|(_, column)| {
+                row.and_then(|row| row.get(column))
+                    .unwrap_or_default()
+                    .to_owned()
+            }));
+            record
+        };
+        match self {
+            LookupOutput::Table { columns, lines } => lines.push(record(*columns)),
+            LookupOutput::Json { out } => {
+                #[derive(serde::Serialize)]
+                struct Lookup<'a> {
+                    ip: IpAddr,
+                    #[serde(flatten)]
+                    row: Option<&'a Row>,
+                }
+                serde_json::to_writer(&mut *out, &Lookup { ip: addr, row })?;
+                writeln!(out)?;
+            }
+            LookupOutput::Csv { columns, writer } => writer.write_record(record(*columns))?,
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(), Box<dyn Error>> {
+        match self {
+            LookupOutput::Table { lines, .. } => write_table(&lines)?,
+            LookupOutput::Json { mut out } => out.flush()?,
+            LookupOutput::Csv { mut writer, .. } => writer.flush()?,
+        }
+        Ok(())
+    }
+}
+
+fn write_table(lines: &[Vec<String>]) -> io::Result<()> {
+    let mut widths = Vec::new();
+    for line in lines {
+        widths.resize(widths.len().max(line.len()), 0);
+        for (width, cell) in widths.iter_mut().zip(line) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut out = io::stdout().lock();
+    for line in lines {
+        for (i, (cell, width)) in line.iter().zip(&widths).enumerate() {
+            if i + 1 == line.len() {
+                write!(out, "{cell}")?;
+            } else {
+                write!(out, "{cell:width$}  ")?;
+            }
+        }
+        writeln!(out)?;
+    }
+    out.flush()
+}
+
+fn kind_name(kind: ChangeKind) -> &'static str {
+    match kind {
+        ChangeKind::Added => "added",
+        ChangeKind::Removed => "removed",
+        ChangeKind::Changed => "changed",
+    }
+}
+
+fn diff(
+    format: Format,
+    old: &Database,
+    new: &Database,
+    summary: bool,
+) -> Result<(), Box<dyn Error>> {
+    let mut counts = DiffSummary::default();
+    let mut lines = vec![
+        ["kind", "ip_from", "ip_to", "changes"]
+            .map(String::from)
+            .to_vec(),
+    ];
+    let mut out = io::stdout().lock();
+    let mut csv = match format {
+        Format::Csv if !summary => {
+            let mut writer = csv::Writer::from_writer(io::stdout().lock());
+            writer.write_record(&lines[0])?;
+            Some(writer)
+        }
+        _ => None,
+    };
+
+    for change in ip2proxy::diff(old, new) {
+        let change = change?;
+        counts.add(&change);
+        if summary {
+            continue;
+        }
+
+        let (start, end) = change.range.clone().into_inner();
+        match format {
+            Format::Json => {
+                let json = serde_json::json!({
+                    "kind": kind_name(change.kind),
+                    "ip_from": start,
+                    "ip_to": end,
+                    "old": change.old,
+                    "new": change.new,
+                });
+                writeln!(out, "{json}")?;
+            }
+            Format::Table | Format::Csv => {
+                let changes: Vec<String> = column_names(change.columns())
+                    .map(|(name, column)| {
+                        let value = |row: &Option<Row>| {
+                            row.as_ref()
+                                .and_then(|row| row.get(column))
+                                .unwrap_or_default()
+                                .to_owned()
+                        };
+                        format!("{name}: {} -> {}", value(&change.old), value(&change.new))
+                    })
+                    .collect();
+                let line = vec![
+                    kind_name(change.kind).to_owned(),
+                    start.to_string(),
+                    end.to_string(),
+                    changes.join("; "),
+                ];
+                match csv {
+                    Some(ref mut writer) => writer.write_record(&line)?,
+                    None => lines.push(line),
+                }
+            }
+        }
+    }
+
+    if let Some(mut writer) = csv {
+        writer.flush()?;
+    }
+
+    if !summary {
+        if let Format::Table = format {
+            write_table(&lines)?;
+        }
+        return Ok(out.flush()?);
+    }
+
+    let groups = std::iter::once((String::from("total"), counts.total.clone()))
+        .chain(
+            counts
+                .by_proxy_type
+                .iter()
+                .map(|(key, c)| (format!("proxy_type={key}"), c.clone())),
+        )
+        .chain(
+            counts
+                .by_country
+                .iter()
+                .map(|(key, c)| (format!("country_short={key}"), c.clone())),
+        );
+
+    match format {
+        Format::Json => {
+            let by = |map: &std::collections::BTreeMap<String, ChangeCounts>| {
+                map.iter()
+                    .map(|(key, c)| {
+                        (
+                            key.clone(),
+                            serde_json::json!({
+                                "added": c.added,
+                                "removed": c.removed,
+                                "changed": c.changed,
+                            }),
+                        )
+                    })
+                    .collect::<serde_json::Map<_, _>>()
+            };
+            let json = serde_json::json!({
+                "added": counts.total.added,
+                "removed": counts.total.removed,
+                "changed": counts.total.changed,
+                "by_proxy_type": by(&counts.by_proxy_type),
+                "by_country": by(&counts.by_country),
+            });
+            writeln!(out, "{json}")?;
+            out.flush()?;
+        }
+        Format::Table | Format::Csv => {
+            let mut lines = vec![
+                ["group", "added", "removed", "changed"]
+                    .map(String::from)
+                    .to_vec(),
+            ];
+            lines.extend(groups.map(|(group, c)| {
+                vec![
+                    group,
+                    c.added.to_string(),
+                    c.removed.to_string(),
+                    c.changed.to_string(),
+                ]
+            }));
+            if let Format::Csv = format {
+                let mut writer = csv::Writer::from_writer(out);
+                for line in lines {
+                    writer.write_record(line)?;
+                }
+                writer.flush()?;
+            } else {
+                drop(out);
+                write_table(&lines)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn info(format: Format, db: &Database) -> Result<(), Box<dyn Error>> {
+    let columns: Vec<String> = column_names(db.columns()).map(|(name, _)| name).collect();
+    let fields = [
+        ("package_version", db.package_version().to_string()),
+        ("database_version", db.database_version()),
+        ("columns", columns.join(",")),
+        ("rows_ipv4", db.rows_ipv4().to_string()),
+        ("rows_ipv6", db.rows_ipv6().to_string()),
+        ("product_code", optional(db.product_code())),
+        ("license_code", optional(db.license_code())),
+        ("file_size", optional(db.file_size())),
+    ];
+
+    match format {
+        Format::Table => write_table(
+            &fields
+                .into_iter()
+                .map(|(key, value)| vec![key.to_owned(), value])
+                .collect::<Vec<_>>(),
+        )?,
+        Format::Json => {
+            let json = serde_json::json!({
+                "package_version": db.package_version(),
+                "database_version": db.database_version(),
+                "columns": columns,
+                "rows_ipv4": db.rows_ipv4(),
+                "rows_ipv6": db.rows_ipv6(),
+                "product_code": db.product_code(),
+                "license_code": db.license_code(),
+                "file_size": db.file_size(),
+            });
+            println!("{json}");
+        }
+        Format::Csv => {
+            let mut writer = csv::Writer::from_writer(io::stdout().lock());
+            writer.write_record(fields.iter().map(|(key, _)| key))?;
+            writer.write_record(fields.iter().map(|(_, value)| value))?;
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map_or_else(|| String::from("-"), |value| value.to_string())
+}
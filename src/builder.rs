@@ -0,0 +1,513 @@
+use std::{
+    collections::HashMap,
+    io,
+    io::Write,
+    net::IpAddr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use byteorder::{LE, WriteBytesExt as _};
+
+use crate::{
+    COLUMN_LAYOUT, Columns, Database, Error, InvalidInput, PRODUCT_IP2PROXY, PX, Row, date,
+};
+
+/// Writes IP2Proxy BIN database files, for example to create test fixtures
+/// or private datasets.
+///
+/// Files are written with the extended header of newer releases, declaring
+/// the product code and file size.
+///
+/// Ranges must be added in ascending order and must not overlap. Gaps
+/// between ranges are filled with empty rows. Fields that are not set in
+/// a [`Row`] are written as `-`.
+///
+/// # Example
+///
+/// ```
+/// use ip2proxy::{Columns, DatabaseBuilder, Row};
+///
+/// let mut row = Row::default();
+/// row.proxy_type = Some(String::from("VPN"));
+/// row.country_short = Some(String::from("DE"));
+/// row.country_long = Some(String::from("Germany"));
+///
+/// let mut builder = DatabaseBuilder::new(2)?;
+/// builder.date(24, 5, 1);
+/// builder.push("10.0.0.0".parse()?, "10.0.0.255".parse()?, row.clone())?;
+///
+/// let db = builder.build()?;
+/// assert_eq!(db.database_version(), "24.5.1");
+/// assert_eq!(db.query("10.0.0.1".parse()?, Columns::all())?, Some(row));
+/// assert_eq!(
+///     db.query("10.0.1.1".parse()?, Columns::all())?.and_then(|r| r.proxy_type),
+///     Some(String::from("-"))
+/// );
+/// # Ok::<_, Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct DatabaseBuilder {
+    pub(crate) px: u8,
+    pub(crate) columns: Columns,
+    year: u8,
+    month: u8,
+    day: u8,
+    ipv4: Vec<Range>,
+    ipv6: Vec<Range>,
+}
+
+#[derive(Debug, Clone)]
+struct Range {
+    start: u128,
+    end: u128,
+    row: Row,
+}
+
+impl DatabaseBuilder {
+    /// Create a builder for a database with the columns of the given
+    /// package version (PX1 - PX11).
+    ///
+    /// # Errors
+    ///
+    /// * Unsupported package version ([`Error::UnsupportedPackage`]).
+    pub fn new(px: u8) -> Result<DatabaseBuilder, Error> {
+        let columns = PX
+            .get(usize::from(px))
+            .copied()
+            .unwrap_or_else(Columns::empty);
+        if columns.is_empty() {
+            return Err(Error::UnsupportedPackage { px });
+        }
+
+        Ok(DatabaseBuilder {
+            px,
+            columns,
+            year: 0,
+            month: 0,
+            day: 0,
+            ipv4: Vec::new(),
+            ipv6: Vec::new(),
+        })
+    }
+
+    /// Set the database creation date. Convention is `16` for `2016`.
+    pub fn date(&mut self, year: u8, month: u8, day: u8) -> &mut DatabaseBuilder {
+        self.year = year;
+        self.month = month;
+        self.day = day;
+        self
+    }
+
+    /// Set the database creation date to the UTC date of `time`, for
+    /// example [`SystemTime::now()`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::time::{Duration, UNIX_EPOCH};
+    ///
+    /// use ip2proxy::DatabaseBuilder;
+    ///
+    /// let mut builder = DatabaseBuilder::new(1)?;
+    /// builder.date_of(UNIX_EPOCH + Duration::from_secs(1479340800));
+    /// let db = builder.build()?;
+    /// assert_eq!((db.year(), db.month(), db.day()), (16, 11, 17));
+    /// # Ok::<_, Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn date_of(&mut self, time: SystemTime) -> &mut DatabaseBuilder {
+        let days = match time.duration_since(UNIX_EPOCH) {
+            Ok(since) => (since.as_secs() / 86400) as i64,
+            Err(err) => -(err.duration().as_secs().div_ceil(86400) as i64),
+        };
+        let (year, month, day) = date::civil_from_days(days);
+        self.date(year.rem_euclid(100) as u8, month, day) // rem_euclid(100) < 100
+    }
+
+    /// Add a range of addresses, including both bounds.
+    ///
+    /// IPv4 ranges go into the IPv4 table, and IPv6 ranges into the IPv6
+    /// table. Lookups of IPv4-mapped, 6to4 and Teredo addresses use the
+    /// IPv4 table.
+    ///
+    /// The highest address of each family is reserved, and shares the row
+    /// of the address before it.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`Error::InvalidInput`] if:
+    ///
+    /// * The bounds are not of the same address family, or out of order.
+    /// * The range is not strictly after the previous range of the same
+    ///   address family.
+    /// * The range starts at the reserved highest address.
+    pub fn push(&mut self, start: IpAddr, end: IpAddr, row: Row) -> Result<(), Error> {
+        let (ranges, start, end, max) = match (start, end) {
+            (IpAddr::V4(start), IpAddr::V4(end)) => (
+                &mut self.ipv4,
+                u128::from(u32::from(start)),
+                u128::from(u32::from(end)),
+                u128::from(u32::MAX),
+            ),
+            (IpAddr::V6(start), IpAddr::V6(end)) => (
+                &mut self.ipv6,
+                u128::from(start),
+                u128::from(end),
+                u128::MAX,
+            ),
+            _ => {
+                return Err(Error::InvalidInput(InvalidInput::MixedFamilies));
+            }
+        };
+
+        if start > end {
+            return Err(Error::InvalidInput(InvalidInput::RangeReversed));
+        }
+        if ranges.last().is_some_and(|last| start <= last.end) {
+            return Err(Error::InvalidInput(InvalidInput::RangeNotAscending));
+        }
+        if start == max {
+            return Err(Error::InvalidInput(InvalidInput::ReservedAddress));
+        }
+
+        ranges.push(Range { start, end, row });
+        Ok(())
+    }
+
+    /// Build the database in memory.
+    ///
+    /// # Errors
+    ///
+    /// See [`DatabaseBuilder::write()`].
+    pub fn build(&self) -> Result<Database<Vec<u8>>, Error> {
+        let mut bytes = Vec::new();
+        self.write(&mut bytes)?;
+        Database::from_bytes(bytes)
+    }
+
+    /// Write the database file.
+    ///
+    /// # Errors
+    ///
+    /// * Error while writing ([`Error::Io`]).
+    /// * A string is longer than 255 bytes, or a country code is longer
+    ///   than 2 bytes ([`Error::InvalidInput`]).
+    /// * The database would exceed the maximum file size of 4 GiB
+    ///   ([`Error::InvalidInput`]).
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        let ipv4 = fill(&self.ipv4, u128::from(u32::MAX));
+        let ipv6 = fill(&self.ipv6, u128::MAX);
+
+        let num_columns = 1 + COLUMN_LAYOUT
+            .iter()
+            .filter(|col| self.columns.intersects(**col))
+            .count();
+        let row_size_ipv4 = 4 + (num_columns - 1) * 4;
+        let row_size_ipv6 = 16 + (num_columns - 1) * 4;
+
+        // Including the extra row that marks the end of the last range.
+        let rows_ipv4 = ipv4.len() + 1;
+        let rows_ipv6 = ipv6.len() + 1;
+
+        let index_ptr_ipv4 = HEADER_AREA_LEN;
+        let index_ptr_ipv6 = index_ptr_ipv4 + INDEX_LEN;
+        let base_ptr_ipv4 = index_ptr_ipv6 + INDEX_LEN;
+        let base_ptr_ipv6 = base_ptr_ipv4 + rows_ipv4 as u64 * row_size_ipv4 as u64;
+        let pool_ptr = base_ptr_ipv6 + rows_ipv6 as u64 * row_size_ipv6 as u64;
+
+        let mut pool = Pool::new(pool_ptr);
+        let ptrs_ipv4 = self.pointers(&mut pool, &ipv4)?;
+        let ptrs_ipv6 = self.pointers(&mut pool, &ipv6)?;
+
+        // Header. Pointers are 1-based.
+        writer.write_u8(self.px)?;
+        writer.write_u8(num_columns as u8)?; // num_columns <= MAX_COLUMNS
+        writer.write_u8(self.year)?;
+        writer.write_u8(self.month)?;
+        writer.write_u8(self.day)?;
+        writer.write_u32::<LE>(to_u32(rows_ipv4 as u64)?)?;
+        writer.write_u32::<LE>(to_u32(base_ptr_ipv4 + 1)?)?;
+        writer.write_u32::<LE>(to_u32(rows_ipv6 as u64)?)?;
+        writer.write_u32::<LE>(to_u32(base_ptr_ipv6 + 1)?)?;
+        writer.write_u32::<LE>(to_u32(index_ptr_ipv4 + 1)?)?;
+        writer.write_u32::<LE>(to_u32(index_ptr_ipv6 + 1)?)?;
+        writer.write_u8(PRODUCT_IP2PROXY)?;
+        writer.write_u8(0)?; // license code
+        writer.write_u32::<LE>(to_u32(pool_ptr + pool.bytes.len() as u64)?)?;
+        writer.write_all(&[0; HEADER_AREA_LEN as usize - crate::EXTENDED_HEADER_LEN])?;
+
+        // Index tables.
+        write_index(&mut writer, &ipv4, 32)?;
+        write_index(&mut writer, &ipv6, 128)?;
+
+        // Row tables.
+        for (start, ptrs) in ipv4.iter().map(|(start, _)| *start).zip(&ptrs_ipv4) {
+            writer.write_u32::<LE>(start as u32)?; // start <= u32::MAX
+            write_pointers(&mut writer, ptrs)?;
+        }
+        writer.write_u32::<LE>(u32::MAX)?;
+        write_pointers(&mut writer, ptrs_ipv4.last().expect("non-empty"))?;
+
+        for (start, ptrs) in ipv6.iter().map(|(start, _)| *start).zip(&ptrs_ipv6) {
+            writer.write_u128::<LE>(start)?;
+            write_pointers(&mut writer, ptrs)?;
+        }
+        writer.write_u128::<LE>(u128::MAX)?;
+        write_pointers(&mut writer, ptrs_ipv6.last().expect("non-empty"))?;
+
+        // Strings.
+        writer.write_all(&pool.bytes)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn pointers(
+        &self,
+        pool: &mut Pool,
+        rows: &[(u128, Option<&Row>)],
+    ) -> Result<Vec<Vec<u32>>, Error> {
+        rows.iter()
+            .map(|(_, row)| {
+                let field = |column| row.and_then(|row| row.get(column)).unwrap_or("-");
+                let mut ptrs = Vec::with_capacity(COLUMN_LAYOUT.len());
+                for col in COLUMN_LAYOUT {
+                    if self.columns.intersects(col) {
+                        ptrs.push(if col.contains(Columns::COUNTRY_SHORT) {
+                            pool.country(
+                                field(Columns::COUNTRY_SHORT),
+                                field(Columns::COUNTRY_LONG),
+                            )?
+                        } else {
+                            pool.string(field(col))?
+                        });
+                    }
+                }
+                Ok(ptrs)
+            })
+            .collect()
+    }
+}
+
+/// Length of the header section, including reserved space.
+const HEADER_AREA_LEN: u64 = 64;
+
+/// Length of an index table, with a row range for each possible value of
+/// the upper 16 bits.
+const INDEX_LEN: u64 = (1 << 16) * 8;
+
+/// Returns the start and row of each range, with gaps filled, such that the
+/// ranges cover all addresses up to max.
+fn fill(ranges: &[Range], max: u128) -> Vec<(u128, Option<&Row>)> {
+    let mut rows = Vec::with_capacity(ranges.len() + 1);
+    let mut next = 0;
+    for range in ranges {
+        if next < range.start {
+            rows.push((next, None));
+        }
+        rows.push((range.start, Some(&range.row)));
+        next = range.end.saturating_add(1);
+    }
+    if next < max {
+        rows.push((next, None));
+    }
+    rows
+}
+
+fn write_index<W: Write>(
+    mut writer: W,
+    rows: &[(u128, Option<&Row>)],
+    bits: u32,
+) -> Result<(), Error> {
+    let shift = bits - 16;
+    let row_containing = |addr: u128| rows.partition_point(|(start, _)| *start <= addr) - 1; // rows[0].0 == 0
+    for upper in 0..(1 << 16) {
+        let first = upper << shift;
+        let last = first | ((1 << shift) - 1);
+        writer.write_u32::<LE>(to_u32(row_containing(first) as u64)?)?;
+        writer.write_u32::<LE>(to_u32(row_containing(last) as u64)?)?;
+    }
+    Ok(())
+}
+
+fn write_pointers<W: Write>(mut writer: W, ptrs: &[u32]) -> io::Result<()> {
+    for ptr in ptrs {
+        writer.write_u32::<LE>(*ptr)?;
+    }
+    Ok(())
+}
+
+fn to_u32(n: u64) -> Result<u32, Error> {
+    u32::try_from(n).map_err(|_| Error::InvalidInput(InvalidInput::DatabaseTooLarge))
+}
+
+/// Deduplicated strings.
+#[derive(Debug)]
+struct Pool {
+    base_ptr: u64,
+    bytes: Vec<u8>,
+    strings: HashMap<String, u32>,
+    countries: HashMap<(String, String), u32>,
+}
+
+impl Pool {
+    fn new(base_ptr: u64) -> Pool {
+        Pool {
+            base_ptr,
+            bytes: Vec::new(),
+            strings: HashMap::new(),
+            countries: HashMap::new(),
+        }
+    }
+
+    fn ptr(&self) -> Result<u32, Error> {
+        to_u32(self.base_ptr + self.bytes.len() as u64)
+    }
+
+    fn push(&mut self, s: &str) -> Result<(), Error> {
+        let len =
+            u8::try_from(s.len()).map_err(|_| Error::InvalidInput(InvalidInput::StringTooLong))?;
+        self.bytes.push(len);
+        self.bytes.extend_from_slice(s.as_bytes());
+        Ok(())
+    }
+
+    fn string(&mut self, s: &str) -> Result<u32, Error> {
+        if let Some(ptr) = self.strings.get(s) {
+            return Ok(*ptr);
+        }
+        let ptr = self.ptr()?;
+        self.push(s)?;
+        self.strings.insert(s.to_owned(), ptr);
+        Ok(ptr)
+    }
+
+    fn country(&mut self, short: &str, long: &str) -> Result<u32, Error> {
+        if let Some(ptr) = self.countries.get(&(short.to_owned(), long.to_owned())) {
+            return Ok(*ptr);
+        }
+        if short.len() > 2 {
+            return Err(Error::InvalidInput(InvalidInput::CountryCodeTooLong));
+        }
+        // Short name is padded to a fixed size, so that the long name
+        // always follows at offset 3.
+        let ptr = self.ptr()?;
+        self.push(short)?;
+        self.bytes.resize(self.bytes.len() + 2 - short.len(), b' ');
+        self.push(long)?;
+        self.countries
+            .insert((short.to_owned(), long.to_owned()), ptr);
+        Ok(ptr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, net::Ipv6Addr};
+
+    use super::*;
+    use crate::tests::{SAMPLE, SAMPLE_COUNTRIES};
+
+    #[test]
+    fn test_roundtrip_sample() {
+        let sample = Database::open(SAMPLE).unwrap();
+
+        let mut builder = DatabaseBuilder::new(sample.package_version()).unwrap();
+        builder.date(sample.year(), sample.month(), sample.day());
+        for res in sample.iter(Columns::all()) {
+            let (start, end, row) = res.unwrap();
+            builder.push(start, end, row).unwrap();
+        }
+
+        let path =
+            std::env::temp_dir().join(format!("ip2proxy-roundtrip-{}.bin", std::process::id()));
+        builder.write(fs::File::create(&path).unwrap()).unwrap();
+        let db = Database::open(&path).unwrap();
+        let report = db.validate().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(report.is_ok(), "{report:?}");
+
+        assert_eq!(db.package_version(), sample.package_version());
+        assert_eq!(db.database_version(), sample.database_version());
+        assert_eq!(db.rows_ipv4(), sample.rows_ipv4());
+        assert_eq!(db.rows_ipv6(), sample.rows_ipv6());
+
+        let expected: Vec<_> = sample.iter(Columns::all()).map(Result::unwrap).collect();
+        let actual: Vec<_> = db.iter(Columns::all()).map(Result::unwrap).collect();
+        assert_eq!(actual, expected);
+
+        // Lookups through the written index.
+        for (addr, country) in SAMPLE_COUNTRIES {
+            let row = db.query(addr.parse().unwrap(), Columns::COUNTRY_SHORT);
+            assert_eq!(
+                row.unwrap().and_then(|row| row.country_short).as_deref(),
+                Some(country),
+                "{addr}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        assert!(matches!(
+            DatabaseBuilder::new(12),
+            Err(Error::UnsupportedPackage { px: 12 })
+        ));
+
+        let mut builder = DatabaseBuilder::new(2).unwrap();
+        let addr = |s: &str| s.parse::<IpAddr>().unwrap();
+        builder
+            .push(addr("10.0.0.0"), addr("10.0.0.255"), Row::default())
+            .unwrap();
+        for (start, end, expected) in [
+            ("10.0.1.0", "::1", InvalidInput::MixedFamilies),
+            ("10.0.1.255", "10.0.1.0", InvalidInput::RangeReversed),
+            ("10.0.0.128", "10.0.1.0", InvalidInput::RangeNotAscending),
+            (
+                "255.255.255.255",
+                "255.255.255.255",
+                InvalidInput::ReservedAddress,
+            ),
+        ] {
+            assert!(
+                matches!(
+                    builder.push(addr(start), addr(end), Row::default()),
+                    Err(Error::InvalidInput(err)) if err == expected
+                ),
+                "{start} - {end}"
+            );
+        }
+
+        let mut row = Row {
+            country_short: Some(String::from("DEU")),
+            ..Row::default()
+        };
+        builder
+            .push(addr("10.0.1.0"), addr("10.0.1.255"), row.clone())
+            .unwrap();
+        assert!(matches!(
+            builder.build(),
+            Err(Error::InvalidInput(InvalidInput::CountryCodeTooLong))
+        ));
+
+        let mut builder = DatabaseBuilder::new(2).unwrap();
+        row.country_short = None;
+        row.country_long = Some("x".repeat(256));
+        builder
+            .push(addr("10.0.0.0"), addr("10.0.0.255"), row)
+            .unwrap();
+        assert!(matches!(
+            builder.build(),
+            Err(Error::InvalidInput(InvalidInput::StringTooLong))
+        ));
+    }
+
+    #[test]
+    fn test_empty() {
+        let db = DatabaseBuilder::new(11).unwrap().build().unwrap();
+        let row = db
+            .query(IpAddr::V6(Ipv6Addr::from(u128::MAX)), Columns::all())
+            .unwrap()
+            .unwrap();
+        assert_eq!(row.provider, Some(String::from("-")));
+        assert_eq!(row.is_proxy(), Some(false));
+    }
+}
@@ -0,0 +1,313 @@
+use std::{
+    collections::BTreeMap,
+    net::IpAddr,
+    num::NonZeroUsize,
+    path::Path,
+    sync::{
+        Arc, Mutex, MutexGuard, PoisonError,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use lru::LruCache;
+use positioned_io::RandomAccessFile;
+
+use crate::{Columns, Database, Error, ReloadableDatabase, Row, Source, clamp_ip, normalize_ip};
+
+/// An IP2Proxy BIN database with a cache of recently matched rows.
+///
+/// Entries are keyed by the matched row, not by the address, so all
+/// addresses of a range share a single entry. Each entry holds a shared
+/// [`Row`] for every set of [`Columns`] that was queried, so cache hits
+/// neither read from the source nor copy any strings. The least recently
+/// used entry is evicted when the capacity is exceeded.
+///
+/// Like [`ReloadableDatabase`], the database can be replaced at runtime.
+/// This invalidates all entries.
+///
+/// # Example
+///
+/// ```
+/// use std::num::NonZeroUsize;
+///
+/// use ip2proxy::{CachedDatabase, Columns};
+///
+/// let capacity = NonZeroUsize::new(1000).unwrap();
+/// let db = CachedDatabase::open("data/IP2PROXY-IP-PROXYTYPE-COUNTRY-REGION-CITY-ISP.SAMPLE.BIN", capacity)?;
+///
+/// let row = db.query("1.0.0.1".parse()?, Columns::PROXY_TYPE)?.unwrap();
+/// assert_eq!(row.proxy_type.as_deref(), Some("DCH"));
+///
+/// // Same range.
+/// let row = db.query("1.0.0.2".parse()?, Columns::PROXY_TYPE)?.unwrap();
+/// assert_eq!(row.proxy_type.as_deref(), Some("DCH"));
+///
+/// let stats = db.stats();
+/// assert_eq!((stats.hits, stats.misses), (1, 1));
+/// # Ok::<_, Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+pub struct CachedDatabase<R = RandomAccessFile> {
+    inner: ReloadableDatabase<R>,
+    cache: Mutex<Cache>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+/// Counters of a [`CachedDatabase`].
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct CacheStats {
+    /// Number of lookups answered from the cache.
+    pub hits: u64,
+    /// Number of lookups that had to read from the database.
+    pub misses: u64,
+    /// Number of cached ranges.
+    pub len: usize,
+}
+
+impl CachedDatabase {
+    /// Open a database file, with a cache for up to `capacity` ranges.
+    ///
+    /// # Errors
+    ///
+    /// See [`Database::open()`].
+    pub fn open<P: AsRef<Path>>(path: P, capacity: NonZeroUsize) -> Result<Self, Error> {
+        Database::open(path).map(|db| CachedDatabase::new(db, capacity))
+    }
+
+    /// Open a new database file and swap it in, if it is compatible with
+    /// the current database. See [`CachedDatabase::replace()`].
+    ///
+    /// # Errors
+    ///
+    /// See [`ReloadableDatabase::reload()`].
+    pub fn reload<P: AsRef<Path>>(&self, path: P) -> Result<Arc<Database>, Error> {
+        self.replace(Database::open(path)?)
+    }
+}
+
+impl<R> CachedDatabase<R> {
+    /// Wrap a database, with a cache for up to `capacity` ranges.
+    pub fn new(db: Database<R>, capacity: NonZeroUsize) -> CachedDatabase<R> {
+        CachedDatabase {
+            inner: ReloadableDatabase::new(db),
+            cache: Mutex::new(Cache {
+                generation: 0,
+                entries: LruCache::new(capacity),
+                starts: BTreeMap::new(),
+            }),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Get the current database.
+    ///
+    /// See [`ReloadableDatabase::load()`].
+    pub fn load(&self) -> Arc<Database<R>> {
+        self.inner.load()
+    }
+
+    /// Swap in a new database and clear the cache. Returns the previous
+    /// database.
+    ///
+    /// Lookups that are in progress while the database is replaced do
+    /// not add their results to the cache.
+    ///
+    /// # Errors
+    ///
+    /// * The new database is not compatible with the current database
+    ///   ([`Error::IncompatibleDatabase`]).
+    pub fn replace(&self, db: Database<R>) -> Result<Arc<Database<R>>, Error> {
+        let mut cache = self.lock();
+        let prev = self.inner.replace(db)?;
+        cache.generation += 1;
+        cache.entries.clear();
+        cache.starts.clear();
+        Ok(prev)
+    }
+
+    /// Remove all entries from the cache. Counters are not reset.
+    pub fn clear(&self) {
+        let mut cache = self.lock();
+        cache.entries.clear();
+        cache.starts.clear();
+    }
+
+    /// Get the maximum number of cached ranges.
+    pub fn capacity(&self) -> NonZeroUsize {
+        self.lock().entries.cap()
+    }
+
+    /// Get the hit and miss counters, and the number of cached ranges.
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            len: self.lock().entries.len(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Cache> {
+        self.cache.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<R: Source> CachedDatabase<R> {
+    /// Look up information for an IP address, from the cache if possible.
+    ///
+    /// Rows are cached separately for each set of [`Columns`], and only
+    /// the selected columns are read on a miss. A lookup of a cached range
+    /// with a different set of columns is a miss, but does not take up
+    /// another entry of the capacity.
+    ///
+    /// See [`Database::query()`].
+    ///
+    /// # Errors
+    ///
+    /// * Error while reading from the source.
+    /// * Invalid row or string data.
+    pub fn query(&self, addr: IpAddr, query: Columns) -> Result<Option<Arc<Row>>, Error> {
+        let addr = clamp_ip(normalize_ip(addr));
+        let query = query & Columns::all();
+
+        let (generation, db) = {
+            let mut cache = self.lock();
+            if let Some(row) = cache.get(addr, query) {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                return Ok(Some(row));
+            }
+            (cache.generation, self.inner.load())
+        };
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        let Some((index, raw)) = db.search_row(addr, 0)? else {
+            return Ok(None);
+        };
+        let row = Arc::new(db.read_row(raw.cols(), query)?);
+
+        let mut cache = self.lock();
+        if cache.generation == generation {
+            let range = raw.range();
+            cache.insert(index, *range.start(), *range.end(), query, Arc::clone(&row));
+        }
+        Ok(Some(row))
+    }
+}
+
+impl<R> From<Database<R>> for CachedDatabase<R> {
+    /// Wrap a database, with a cache for up to 1024 ranges.
+    fn from(db: Database<R>) -> CachedDatabase<R> {
+        CachedDatabase::new(db, NonZeroUsize::new(1024).expect("non-zero"))
+    }
+}
+
+#[derive(Debug)]
+struct Cache {
+    /// Incremented whenever the database is replaced.
+    generation: u64,
+    /// Cached rows, keyed by table (`true` for IPv6) and row index.
+    entries: LruCache<(bool, u32), Entry>,
+    /// Row index of each cached range, keyed by the first address.
+    starts: BTreeMap<IpAddr, u32>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    start: IpAddr,
+    end: IpAddr,
+    /// Row of the range for each queried set of columns.
+    rows: Vec<(Columns, Arc<Row>)>,
+}
+
+impl Cache {
+    fn get(&mut self, addr: IpAddr, query: Columns) -> Option<Arc<Row>> {
+        let (start, index) = self.starts.range(..=addr).next_back()?;
+        let key = (start.is_ipv6(), *index);
+        // Only the matching entry counts as used.
+        if addr > self.entries.peek(&key)?.end {
+            return None;
+        }
+        self.entries
+            .get(&key)?
+            .rows
+            .iter()
+            .find(|(columns, _)| *columns == query)
+            .map(|(_, row)| Arc::clone(row))
+    }
+
+    fn insert(&mut self, index: u32, start: IpAddr, end: IpAddr, query: Columns, row: Arc<Row>) {
+        let key = (start.is_ipv6(), index);
+        if let Some(entry) = self.entries.get_mut(&key) {
+            if !entry.rows.iter().any(|(columns, _)| *columns == query) {
+                entry.rows.push((query, row));
+            }
+            return;
+        }
+        let entry = Entry {
+            start,
+            end,
+            rows: vec![(query, row)],
+        };
+        if let Some((_, evicted)) = self.entries.push(key, entry) {
+            self.starts.remove(&evicted.start);
+        }
+        self.starts.insert(start, index);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache() {
+        let path = "data/IP2PROXY-IP-PROXYTYPE-COUNTRY-REGION-CITY-ISP.SAMPLE.BIN";
+        let db = CachedDatabase::open(path, NonZeroUsize::new(2).unwrap()).unwrap();
+        let plain = Database::open(path).unwrap();
+
+        let query = Columns::COUNTRY_SHORT | Columns::CITY;
+        for addr in [
+            "1.0.0.1",
+            "1.0.0.255",
+            "1.0.1.1",
+            "::ffff:1.0.0.7",
+            "1.0.16.1", // evicts 1.0.1.0 - 1.0.15.255
+            "1.0.1.5",
+        ] {
+            let addr = addr.parse().unwrap();
+            assert_eq!(
+                db.query(addr, query).unwrap().as_deref(),
+                plain.query(addr, query).unwrap().as_ref()
+            );
+        }
+        assert_eq!(
+            db.stats(),
+            CacheStats {
+                hits: 2,
+                misses: 4,
+                len: 2,
+            }
+        );
+
+        // Hits share the cached row.
+        let addr = "1.0.16.2".parse().unwrap();
+        let row = db.query(addr, query).unwrap().unwrap();
+        assert!(Arc::ptr_eq(&row, &db.query(addr, query).unwrap().unwrap()));
+        assert_eq!(db.stats().hits, 4);
+
+        // Other columns are read on a miss, within the same entry.
+        let row = db.query(addr, Columns::PROXY_TYPE).unwrap().unwrap();
+        assert_eq!(
+            Some(&*row),
+            plain.query(addr, Columns::PROXY_TYPE).unwrap().as_ref()
+        );
+        assert_eq!(db.stats().misses, 5);
+        assert_eq!(db.stats().len, 2);
+
+        db.reload(path).unwrap();
+        assert_eq!(db.stats().len, 0);
+        db.query("1.0.1.5".parse().unwrap(), query).unwrap();
+        assert_eq!(db.stats().misses, 6);
+    }
+}
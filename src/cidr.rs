@@ -0,0 +1,164 @@
+use std::{
+    cmp::min,
+    fmt,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    ops::RangeInclusive,
+};
+
+/// An IP network in CIDR notation, like `1.0.0.0/24`.
+///
+/// # Example
+///
+/// ```
+/// use ip2proxy::Cidr;
+///
+/// let cidrs = Cidr::covering("10.0.0.0".parse()?..="10.0.2.255".parse()?);
+/// assert_eq!(cidrs.len(), 2);
+/// assert_eq!(cidrs[0].to_string(), "10.0.0.0/23");
+/// assert_eq!(cidrs[1].to_string(), "10.0.2.0/24");
+/// # Ok::<_, Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl Cidr {
+    /// Create a network from its first address and prefix length.
+    ///
+    /// Returns `None` if the prefix length is too long for the address
+    /// family, or if the address has bits set beyond the prefix.
+    pub fn new(addr: IpAddr, prefix_len: u8) -> Option<Cidr> {
+        let (bits, value) = bits_and_value(addr);
+        if u32::from(prefix_len) > bits || value & host_mask(bits, prefix_len) != 0 {
+            return None;
+        }
+        Some(Cidr { addr, prefix_len })
+    }
+
+    /// Get the smallest list of networks that exactly cover a range of
+    /// addresses, in ascending order.
+    ///
+    /// Returns an empty list if the range is empty or its bounds are of
+    /// different address families.
+    pub fn covering(range: RangeInclusive<IpAddr>) -> Vec<Cidr> {
+        let (start, end) = range.into_inner();
+        let mut cidrs = Vec::new();
+        match (start, end) {
+            (IpAddr::V4(start), IpAddr::V4(end)) => {
+                cover(
+                    u128::from(u32::from(start)),
+                    u128::from(u32::from(end)),
+                    32,
+                    |value, prefix_len| {
+                        cidrs.push(Cidr {
+                            addr: IpAddr::V4(Ipv4Addr::from(value as u32)), // value <= u32::MAX
+                            prefix_len,
+                        })
+                    },
+                );
+            }
+            (IpAddr::V6(start), IpAddr::V6(end)) => {
+                cover(
+                    u128::from(start),
+                    u128::from(end),
+                    128,
+                    |value, prefix_len| {
+                        cidrs.push(Cidr {
+                            addr: IpAddr::V6(Ipv6Addr::from(value)),
+                            prefix_len,
+                        })
+                    },
+                );
+            }
+            _ => (),
+        }
+        cidrs
+    }
+
+    /// Get the first address of the network.
+    pub fn addr(&self) -> IpAddr {
+        self.addr
+    }
+
+    /// Get the prefix length.
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    /// Get the last address of the network.
+    pub fn last(&self) -> IpAddr {
+        let (bits, value) = bits_and_value(self.addr);
+        let last = value | host_mask(bits, self.prefix_len);
+        match self.addr {
+            IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::from(last as u32)), // last <= u32::MAX
+            IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::from(last)),
+        }
+    }
+
+    /// Checks if the network contains an address.
+    pub fn contains(&self, addr: IpAddr) -> bool {
+        self.addr <= addr && addr <= self.last()
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix_len)
+    }
+}
+
+fn bits_and_value(addr: IpAddr) -> (u32, u128) {
+    match addr {
+        IpAddr::V4(addr) => (32, u128::from(u32::from(addr))),
+        IpAddr::V6(addr) => (128, u128::from(addr)),
+    }
+}
+
+fn host_mask(bits: u32, prefix_len: u8) -> u128 {
+    let host_bits = bits - u32::from(prefix_len);
+    u128::MAX.checked_shr(128 - host_bits).unwrap_or(0)
+}
+
+pub(crate) fn cover<F: FnMut(u128, u8)>(mut start: u128, end: u128, bits: u32, mut f: F) {
+    while start <= end {
+        // Largest block that is aligned at start ...
+        let mut host_bits = min(start.trailing_zeros(), bits);
+        // ... and does not extend beyond end.
+        while host_bits > 0 && end - start < host_mask(bits, (bits - host_bits) as u8) {
+            host_bits -= 1;
+        }
+        f(start, (bits - host_bits) as u8); // host_bits <= bits <= 128
+        match (start | host_mask(bits, (bits - host_bits) as u8)).checked_add(1) {
+            Some(next) => start = next,
+            None => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_covering_full_range() {
+        let ipv4 = Cidr::covering("0.0.0.0".parse().unwrap()..="255.255.255.255".parse().unwrap());
+        assert_eq!(ipv4, [Cidr::new("0.0.0.0".parse().unwrap(), 0).unwrap()]);
+
+        let ipv6 = Cidr::covering(
+            "::".parse().unwrap()..="ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff".parse().unwrap(),
+        );
+        assert_eq!(ipv6, [Cidr::new("::".parse().unwrap(), 0).unwrap()]);
+    }
+
+    #[test]
+    fn test_covering_unaligned() {
+        let cidrs = Cidr::covering("10.0.0.1".parse().unwrap()..="10.0.0.6".parse().unwrap());
+        let cidrs: Vec<String> = cidrs.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            cidrs,
+            ["10.0.0.1/32", "10.0.0.2/31", "10.0.0.4/31", "10.0.0.6/32"]
+        );
+    }
+}
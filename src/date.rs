@@ -0,0 +1,47 @@
+//! Dates in the proleptic Gregorian calendar.
+
+/// Days since 1970-01-01 of a civil date.
+pub(crate) fn days_from_civil(year: i64, month: u8, day: u8) -> i64 {
+    let (month, day) = (i64::from(month), i64::from(day));
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// Civil date of a number of days since 1970-01-01, as the inverse of
+/// [`days_from_civil()`].
+pub(crate) fn civil_from_days(days: i64) -> (i64, u8, u8) {
+    let mut year = 1970 + (days * 400).div_euclid(146097);
+    while days_from_civil(year + 1, 1, 1) <= days {
+        year += 1;
+    }
+    while days_from_civil(year, 1, 1) > days {
+        year -= 1;
+    }
+    let month = (1..=12)
+        .rev()
+        .find(|&month| days_from_civil(year, month, 1) <= days)
+        .expect("january");
+    let day = days - days_from_civil(year, month, 1) + 1;
+    (year, month, day as u8) // day <= 31
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_civil_roundtrip() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2016, 11, 17), 1479340800 / 86400);
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(civil_from_days(11016), (2000, 2, 29));
+        for days in -800_000..800_000 {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+    }
+}
@@ -0,0 +1,452 @@
+use std::{
+    collections::BTreeMap,
+    iter::FusedIterator,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    ops::RangeInclusive,
+};
+
+use crate::{Columns, Database, Error, Iter, Row, Source};
+
+/// Compare two releases of a database.
+///
+/// Walks the IPv4 tables of both databases in lockstep, followed by the
+/// IPv6 tables, and yields a [`Change`] for each range of addresses whose
+/// row differs. Adjacent addresses with the same old and new row are
+/// combined into a single range, even if they are split into multiple
+/// ranges in either database.
+///
+/// All supported columns are compared.
+///
+/// # Example
+///
+/// ```
+/// use ip2proxy::{ChangeCounts, Database, DiffSummary, diff};
+///
+/// let old = Database::open("data/IP2PROXY-IP-PROXYTYPE-COUNTRY-REGION-CITY-ISP.SAMPLE.BIN")?;
+/// let new = Database::open("data/IP2PROXY-IP-PROXYTYPE-COUNTRY-REGION-CITY-ISP.SAMPLE.BIN")?;
+///
+/// let mut summary = DiffSummary::default();
+/// for change in diff(&old, &new) {
+///     let change = change?;
+///     println!("{:?} {:?}: {:?}", change.kind, change.range, change.columns());
+///     summary.add(&change);
+/// }
+/// assert_eq!(summary.total, ChangeCounts::default());
+/// # Ok::<_, Box<dyn std::error::Error>>(())
+/// ```
+pub fn diff<'a, R1: Source, R2: Source>(
+    old: &'a Database<R1>,
+    new: &'a Database<R2>,
+) -> Diff<'a, R1, R2> {
+    Diff {
+        ipv4: Some(Lockstep::new(
+            old.iter_ipv4(Columns::all()),
+            new.iter_ipv4(Columns::all()),
+            false,
+        )),
+        ipv6: Some(Lockstep::new(
+            old.iter_ipv6(Columns::all()),
+            new.iter_ipv6(Columns::all()),
+            true,
+        )),
+        pending: None,
+    }
+}
+
+/// Kind of a [`Change`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum ChangeKind {
+    /// The range is now a known proxy.
+    Added,
+    /// The range is no longer a known proxy.
+    Removed,
+    /// The row of the range changed otherwise, for example the proxy type
+    /// or threat of a known proxy.
+    Changed,
+}
+
+/// Range of addresses whose row differs between two databases, yielded by
+/// [`diff()`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct Change {
+    /// Kind of change.
+    pub kind: ChangeKind,
+    /// Addresses with the same old and new row.
+    pub range: RangeInclusive<IpAddr>,
+    /// Row in the old database, if any.
+    pub old: Option<Row>,
+    /// Row in the new database, if any.
+    pub new: Option<Row>,
+}
+
+impl Change {
+    fn new(start: u128, end: u128, ipv6: bool, old: Option<Row>, new: Option<Row>) -> Change {
+        let is_proxy = |row: &Option<Row>| row.as_ref().and_then(Row::is_proxy) == Some(true);
+        let kind = match (is_proxy(&old), is_proxy(&new)) {
+            (false, true) => ChangeKind::Added,
+            (true, false) => ChangeKind::Removed,
+            _ => ChangeKind::Changed,
+        };
+        let addr = |value: u128| {
+            if ipv6 {
+                IpAddr::V6(Ipv6Addr::from(value))
+            } else {
+                IpAddr::V4(Ipv4Addr::from(value as u32)) // value <= u32::MAX
+            }
+        };
+        Change {
+            kind,
+            range: addr(start)..=addr(end),
+            old,
+            new,
+        }
+    }
+
+    /// Get the set of columns whose values differ.
+    pub fn columns(&self) -> Columns {
+        Columns::all()
+            .iter()
+            .filter(|column| {
+                self.old.as_ref().and_then(|row| row.get(*column))
+                    != self.new.as_ref().and_then(|row| row.get(*column))
+            })
+            .fold(Columns::empty(), |acc, column| acc | column)
+    }
+}
+
+/// Number of changed ranges by kind.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct ChangeCounts {
+    /// Number of [`ChangeKind::Added`] ranges.
+    pub added: u64,
+    /// Number of [`ChangeKind::Removed`] ranges.
+    pub removed: u64,
+    /// Number of [`ChangeKind::Changed`] ranges.
+    pub changed: u64,
+}
+
+impl ChangeCounts {
+    fn add(&mut self, kind: ChangeKind) {
+        match kind {
+            ChangeKind::Added => self.added += 1,
+            ChangeKind::Removed => self.removed += 1,
+            ChangeKind::Changed => self.changed += 1,
+        }
+    }
+}
+
+/// Summary of the changes yielded by [`diff()`].
+///
+/// Changes are counted for the proxy type and country of the new row, or
+/// of the old row if the range was removed.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct DiffSummary {
+    /// Total counts.
+    pub total: ChangeCounts,
+    /// Counts by [`Row::proxy_type`].
+    pub by_proxy_type: BTreeMap<String, ChangeCounts>,
+    /// Counts by [`Row::country_short`].
+    pub by_country: BTreeMap<String, ChangeCounts>,
+}
+
+impl DiffSummary {
+    /// Count a change.
+    pub fn add(&mut self, change: &Change) {
+        self.total.add(change.kind);
+
+        let row = match change.kind {
+            ChangeKind::Removed => change.old.as_ref(),
+            ChangeKind::Added | ChangeKind::Changed => change.new.as_ref(),
+        };
+        let key = |column| {
+            row.and_then(|row| row.get(column))
+                .unwrap_or("-")
+                .to_owned()
+        };
+        self.by_proxy_type
+            .entry(key(Columns::PROXY_TYPE))
+            .or_default()
+            .add(change.kind);
+        self.by_country
+            .entry(key(Columns::COUNTRY_SHORT))
+            .or_default()
+            .add(change.kind);
+    }
+}
+
+/// Iterator over the changes between two databases.
+///
+/// See [`diff()`].
+#[derive(Debug)]
+pub struct Diff<'a, R1, R2> {
+    ipv4: Option<Lockstep<'a, R1, R2>>,
+    ipv6: Option<Lockstep<'a, R1, R2>>,
+    pending: Option<Segment>,
+}
+
+impl<R1: Source, R2: Source> Diff<'_, R1, R2> {
+    fn next_segment(&mut self) -> Option<Result<Segment, Error>> {
+        loop {
+            let lockstep = self.ipv4.as_mut().or(self.ipv6.as_mut())?;
+            match lockstep.next() {
+                Some(res) => return Some(res),
+                None => {
+                    if self.ipv4.take().is_none() {
+                        self.ipv6 = None;
+                    }
+                }
+            }
+        }
+    }
+
+    fn stop(&mut self) {
+        self.ipv4 = None;
+        self.ipv6 = None;
+        self.pending = None;
+    }
+}
+
+impl<R1: Source, R2: Source> Iterator for Diff<'_, R1, R2> {
+    type Item = Result<Change, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let segment = match self.next_segment() {
+                Some(Ok(segment)) => Some(segment),
+                Some(Err(err)) => {
+                    self.stop();
+                    return Some(Err(err));
+                }
+                None => None,
+            };
+
+            // Combine adjacent segments with the same rows.
+            let done = match (self.pending.take(), segment) {
+                (Some(mut pending), Some(segment)) if pending.continues(&segment) => {
+                    pending.end = segment.end;
+                    self.pending = Some(pending);
+                    continue;
+                }
+                (Some(done), segment) => {
+                    self.pending = segment;
+                    done
+                }
+                (None, Some(segment)) => {
+                    self.pending = Some(segment);
+                    continue;
+                }
+                (None, None) => return None,
+            };
+
+            if done.old != done.new {
+                return Some(Ok(Change::new(
+                    done.start, done.end, done.ipv6, done.old, done.new,
+                )));
+            }
+        }
+    }
+}
+
+impl<R1: Source, R2: Source> FusedIterator for Diff<'_, R1, R2> {}
+
+/// Addresses with the same rows in both databases.
+#[derive(Debug)]
+struct Segment {
+    start: u128,
+    end: u128,
+    ipv6: bool,
+    old: Option<Row>,
+    new: Option<Row>,
+}
+
+impl Segment {
+    fn continues(&self, next: &Segment) -> bool {
+        self.ipv6 == next.ipv6
+            && self.end.checked_add(1) == Some(next.start)
+            && self.old == next.old
+            && self.new == next.new
+    }
+}
+
+/// Ranges of a table, with one range of lookahead.
+#[derive(Debug)]
+struct Ranges<'a, R> {
+    iter: Iter<'a, R>,
+    peeked: Option<(u128, u128, Row)>,
+}
+
+impl<'a, R: Source> Ranges<'a, R> {
+    fn new(iter: Iter<'a, R>) -> Ranges<'a, R> {
+        Ranges { iter, peeked: None }
+    }
+
+    fn peek(&mut self) -> Result<Option<&(u128, u128, Row)>, Error> {
+        if self.peeked.is_none() {
+            self.peeked = match self.iter.next() {
+                Some(res) => {
+                    let (start, end, row) = res?;
+                    Some((value(start), value(end), row))
+                }
+                None => None,
+            };
+        }
+        Ok(self.peeked.as_ref())
+    }
+
+    /// Get the row at `pos` and the last address with the same row, or
+    /// the address before the next range if there is no row at `pos`.
+    fn at(&mut self, pos: u128) -> Result<Option<(u128, Option<Row>)>, Error> {
+        while self.peek()?.is_some_and(|(_, end, _)| *end < pos) {
+            self.peeked = None;
+        }
+        Ok(match self.peek()? {
+            Some((start, end, row)) if *start <= pos => Some((*end, Some(row.clone()))),
+            Some((start, _, _)) => Some((start - 1, None)), // start > pos
+            None => None,
+        })
+    }
+}
+
+fn value(addr: IpAddr) -> u128 {
+    match addr {
+        IpAddr::V4(addr) => u128::from(u32::from(addr)),
+        IpAddr::V6(addr) => u128::from(addr),
+    }
+}
+
+/// Walks two tables of the same address family in lockstep.
+#[derive(Debug)]
+struct Lockstep<'a, R1, R2> {
+    old: Ranges<'a, R1>,
+    new: Ranges<'a, R2>,
+    ipv6: bool,
+    pos: Option<u128>,
+}
+
+impl<'a, R1: Source, R2: Source> Lockstep<'a, R1, R2> {
+    fn new(old: Iter<'a, R1>, new: Iter<'a, R2>, ipv6: bool) -> Lockstep<'a, R1, R2> {
+        Lockstep {
+            old: Ranges::new(old),
+            new: Ranges::new(new),
+            ipv6,
+            pos: Some(0),
+        }
+    }
+}
+
+impl<R1: Source, R2: Source> Lockstep<'_, R1, R2> {
+    fn step(&mut self, pos: u128) -> Result<Option<Segment>, Error> {
+        let old = self.old.at(pos)?;
+        let new = self.new.at(pos)?;
+        let (end, old, new) = match (old, new) {
+            (None, None) => return Ok(None),
+            (Some((old_end, old)), None) => (old_end, old, None),
+            (None, Some((new_end, new))) => (new_end, None, new),
+            (Some((old_end, old)), Some((new_end, new))) => (old_end.min(new_end), old, new),
+        };
+        Ok(Some(Segment {
+            start: pos,
+            end,
+            ipv6: self.ipv6,
+            old,
+            new,
+        }))
+    }
+}
+
+impl<R1: Source, R2: Source> Iterator for Lockstep<'_, R1, R2> {
+    type Item = Result<Segment, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let pos = self.pos?;
+            let res = self.step(pos);
+            match res {
+                Ok(Some(segment)) => {
+                    self.pos = segment.end.checked_add(1);
+                    if segment.old.is_none() && segment.new.is_none() {
+                        continue;
+                    }
+                    return Some(Ok(segment));
+                }
+                Ok(None) => {
+                    self.pos = None;
+                    return None;
+                }
+                Err(err) => {
+                    self.pos = None;
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DatabaseBuilder;
+
+    #[test]
+    fn test_diff() {
+        let old = Database::open("data/IP2PROXY-IP-PROXYTYPE-COUNTRY-REGION-CITY-ISP.SAMPLE.BIN")
+            .unwrap();
+
+        // Rebuild, with the first proxy removed, the second proxy
+        // reclassified, and the rest split into single addresses at the
+        // start of each range.
+        let mut builder = DatabaseBuilder::new(old.package_version()).unwrap();
+        let mut proxies = 0;
+        for res in old.iter(Columns::all()) {
+            let (start, end, mut row) = res.unwrap();
+            if row.is_proxy() == Some(true) {
+                proxies += 1;
+                if proxies == 1 {
+                    row = Row {
+                        proxy_type: Some(String::from("-")),
+                        country_short: Some(String::from("-")),
+                        country_long: Some(String::from("-")),
+                        region: Some(String::from("-")),
+                        city: Some(String::from("-")),
+                        isp: Some(String::from("-")),
+                        ..Row::default()
+                    };
+                } else if proxies == 2 {
+                    row.proxy_type = Some(String::from("TOR"));
+                }
+            }
+            if start != end {
+                builder.push(start, start, row.clone()).unwrap();
+                let next = match start {
+                    IpAddr::V4(start) => IpAddr::V4(Ipv4Addr::from(u32::from(start) + 1)),
+                    IpAddr::V6(start) => IpAddr::V6(Ipv6Addr::from(u128::from(start) + 1)),
+                };
+                builder.push(next, end, row).unwrap();
+            } else {
+                builder.push(start, end, row).unwrap();
+            }
+        }
+        let new = builder.build().unwrap();
+
+        let changes: Vec<_> = diff(&old, &new).map(Result::unwrap).collect();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].kind, ChangeKind::Removed);
+        assert_eq!(changes[1].kind, ChangeKind::Changed);
+        assert_eq!(changes[1].columns(), Columns::PROXY_TYPE);
+
+        let mut summary = DiffSummary::default();
+        for change in &changes {
+            summary.add(change);
+        }
+        assert_eq!(summary.total.removed, 1);
+        assert_eq!(summary.total.changed, 1);
+        assert_eq!(summary.by_proxy_type["TOR"].changed, 1);
+
+        let reverse: Vec<_> = diff(&new, &old).map(Result::unwrap).collect();
+        assert_eq!(reverse.len(), 2);
+        assert_eq!(reverse[0].kind, ChangeKind::Added);
+        assert_eq!(reverse[0].range, changes[0].range);
+    }
+}
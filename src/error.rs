@@ -0,0 +1,191 @@
+use std::{error, fmt, io};
+
+use crate::Columns;
+
+/// Error while reading or building a database.
+///
+/// Converts into [`io::Error`], for compatibility with code that expects
+/// one. [`Error::Io`] is unwrapped, [`Error::InvalidInput`] becomes an
+/// error of kind [`io::ErrorKind::InvalidInput`], and all other variants
+/// become errors of kind [`io::ErrorKind::InvalidData`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// Error while opening or reading from the source, or while writing a
+    /// database.
+    Io(io::Error),
+    /// The database has an unsupported package version. Only PX1 - PX11
+    /// are supported.
+    UnsupportedPackage {
+        /// Package version from the header.
+        px: u8,
+    },
+    /// The header declares an invalid number of columns.
+    InvalidColumnCount {
+        /// Number of columns from the header.
+        num_columns: u8,
+    },
+    /// The extended header declares a different product, like an
+    /// IP2Location database where an IP2Proxy database is expected.
+    UnexpectedProduct {
+        /// Product code from the header.
+        product_code: u8,
+    },
+    /// The length of the data does not match the file size declared in
+    /// the extended header. The file may be truncated or corrupted.
+    FileSizeMismatch {
+        /// File size from the header.
+        expected: u32,
+    },
+    /// An index entry points to rows outside of the table.
+    CorruptIndex,
+    /// A string is not valid UTF-8.
+    InvalidString {
+        /// Offset of the string.
+        ptr: u64,
+    },
+    /// The binary search left the range of rows given by the index,
+    /// because rows are not sorted.
+    SearchOutOfBounds,
+    /// A new database does not have the same package version and columns
+    /// as the database it should replace.
+    IncompatibleDatabase {
+        /// Package version of the current database.
+        expected_px: u8,
+        /// Columns of the current database.
+        expected_columns: Columns,
+        /// Package version of the new database.
+        px: u8,
+        /// Columns of the new database.
+        columns: Columns,
+    },
+    /// Invalid input to [`DatabaseBuilder`](crate::DatabaseBuilder).
+    InvalidInput(InvalidInput),
+}
+
+/// Details of [`Error::InvalidInput`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum InvalidInput {
+    /// The bounds of a range are of different address families.
+    MixedFamilies,
+    /// The start of a range is after its end.
+    RangeReversed,
+    /// A range is not strictly after the previous range of the same
+    /// address family.
+    RangeNotAscending,
+    /// A range starts at the reserved highest address.
+    ReservedAddress,
+    /// A string is longer than 255 bytes.
+    StringTooLong,
+    /// A country code is longer than 2 bytes.
+    CountryCodeTooLong,
+    /// The database would exceed the maximum file size of 4 GiB.
+    DatabaseTooLarge,
+}
+
+impl fmt::Display for InvalidInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            InvalidInput::MixedFamilies => "range bounds of different address families",
+            InvalidInput::RangeReversed => "range start after range end",
+            InvalidInput::RangeNotAscending => "ranges not in ascending order or overlapping",
+            InvalidInput::ReservedAddress => "range starts at reserved highest address",
+            InvalidInput::StringTooLong => "string longer than 255 bytes",
+            InvalidInput::CountryCodeTooLong => "country code longer than 2 bytes",
+            InvalidInput::DatabaseTooLarge => "database too large",
+        })
+    }
+}
+
+impl error::Error for InvalidInput {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => err.fmt(f),
+            Error::UnsupportedPackage { px } => {
+                write!(
+                    f,
+                    "unsupported package version {px}, only px1 - px11 supported"
+                )
+            }
+            Error::InvalidColumnCount { num_columns } => {
+                write!(f, "invalid number of columns: {num_columns}")
+            }
+            Error::UnexpectedProduct { product_code } => {
+                write!(
+                    f,
+                    "unexpected product code {product_code} (1: ip2location, 2: ip2proxy)"
+                )
+            }
+            Error::FileSizeMismatch { expected } => {
+                write!(
+                    f,
+                    "file size does not match header, expected {expected} bytes"
+                )
+            }
+            Error::CorruptIndex => f.write_str("index points outside of table"),
+            Error::InvalidString { ptr } => write!(f, "invalid utf-8 data at {ptr}"),
+            Error::SearchOutOfBounds => f.write_str("binary search out of bounds"),
+            Error::IncompatibleDatabase {
+                expected_px,
+                expected_columns,
+                px,
+                columns,
+            } => {
+                write!(
+                    f,
+                    "new database px{px} with {columns:?} does not match px{expected_px} with {expected_columns:?}"
+                )
+            }
+            Error::InvalidInput(err) => err.fmt(f),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(err: Error) -> io::Error {
+        match err {
+            Error::Io(err) => err,
+            err @ Error::InvalidInput(_) => io::Error::new(io::ErrorKind::InvalidInput, err),
+            err => io::Error::new(io::ErrorKind::InvalidData, err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unsupported_package() {
+        let mut bytes =
+            std::fs::read("data/IP2PROXY-IP-PROXYTYPE-COUNTRY-REGION-CITY-ISP.SAMPLE.BIN").unwrap();
+        bytes[0] = 12;
+        let err = crate::Database::from_bytes(bytes).unwrap_err();
+        assert!(matches!(err, Error::UnsupportedPackage { px: 12 }));
+
+        let err = io::Error::from(err);
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(matches!(
+            err.get_ref().and_then(|err| err.downcast_ref()),
+            Some(Error::UnsupportedPackage { px: 12 })
+        ));
+    }
+}
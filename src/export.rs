@@ -0,0 +1,283 @@
+use std::{
+    io::{self, Write},
+    net::{IpAddr, Ipv6Addr},
+};
+
+use crate::{Columns, Database, Family, Iter, MAPPED_FROM, MAPPED_TO, Row, Source};
+
+impl<R: Source> Database<R> {
+    /// Export the database in the layout of the official IP2Proxy CSV
+    /// distributions.
+    ///
+    /// Rows are written as they are read, so the database does not have to
+    /// fit into memory. Consider wrapping the writer in an
+    /// [`io::BufWriter`].
+    ///
+    /// Addresses are written as decimal numbers. When exporting both tables,
+    /// the layout of the IPv6 variant is used, where IPv4 ranges are given
+    /// as IPv4-mapped IPv6 addresses. Only the selected [`Columns`] are
+    /// written.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ip2proxy::{Columns, Database, Family};
+    ///
+    /// let db = Database::open("data/IP2PROXY-IP-PROXYTYPE-COUNTRY-REGION-CITY-ISP.SAMPLE.BIN")?;
+    ///
+    /// let mut csv = Vec::new();
+    /// db.export_csv(&mut csv, Columns::PX2, Family::Ipv4)?;
+    /// assert!(String::from_utf8(csv)?.contains("\"16777216\",\"16777471\",\"DCH\",\"AU\",\"Australia\"\n"));
+    /// # Ok::<_, Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * Error while reading from the source or writing.
+    /// * Invalid row or string data.
+    #[cfg(feature = "csv")]
+    pub fn export_csv<W: Write>(
+        &self,
+        writer: W,
+        query: Columns,
+        family: Family,
+    ) -> io::Result<()> {
+        let columns = query & self.columns();
+        let mut writer = csv::WriterBuilder::new()
+            .quote_style(csv::QuoteStyle::Always)
+            .from_writer(writer);
+
+        // The IPv6 layout gives IPv4 ranges as IPv4-mapped addresses.
+        let addr_value = |addr: IpAddr| match addr {
+            IpAddr::V4(addr) if family == Family::Both => u128::from(addr.to_ipv6_mapped()),
+            IpAddr::V4(addr) => u128::from(u32::from(addr)),
+            IpAddr::V6(addr) => u128::from(addr),
+        };
+
+        self.for_each_range(query, family, |start, end, row| {
+            let mut record = Vec::with_capacity(2 + columns.iter().count());
+            record.push(addr_value(start).to_string());
+            record.push(addr_value(end).to_string());
+            record.extend(
+                columns
+                    .iter()
+                    .map(|column| row.get(column).unwrap_or_default().to_owned()),
+            );
+            writer.write_record(&record)?;
+            Ok(())
+        })?;
+
+        writer.flush()
+    }
+
+    /// Export the database as JSON Lines, with one object per range.
+    ///
+    /// Each object has the first and last address of the range as
+    /// `ip_from` and `ip_to`, and the selected [`Columns`] of the [`Row`]
+    /// in its `serde` representation. When exporting both tables, ranges
+    /// are ordered like in [`Database::export_csv()`], and IPv4-mapped
+    /// ranges of the IPv6 table are replaced with the ranges from the IPv4
+    /// table.
+    ///
+    /// Rows are written as they are read, so the database does not have to
+    /// fit into memory. Consider wrapping the writer in an
+    /// [`io::BufWriter`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ip2proxy::{Columns, Database, Family};
+    ///
+    /// let db = Database::open("data/IP2PROXY-IP-PROXYTYPE-COUNTRY-REGION-CITY-ISP.SAMPLE.BIN")?;
+    ///
+    /// let mut json = Vec::new();
+    /// db.export_json_lines(&mut json, Columns::PROXY_TYPE, Family::Ipv4)?;
+    /// assert!(String::from_utf8(json)?.contains(r#"{"ip_from":"1.0.0.0","ip_to":"1.0.0.255","proxy_type":"DCH"}"#));
+    /// # Ok::<_, Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * Error while reading from the source or writing.
+    /// * Invalid row or string data.
+    #[cfg(feature = "json")]
+    pub fn export_json_lines<W: Write>(
+        &self,
+        mut writer: W,
+        query: Columns,
+        family: Family,
+    ) -> io::Result<()> {
+        #[derive(serde::Serialize)]
+        struct JsonRange<'a> {
+            ip_from: IpAddr,
+            ip_to: IpAddr,
+            #[serde(flatten)]
+            row: &'a Row,
+        }
+
+        self.for_each_range(query, family, |ip_from, ip_to, row| {
+            serde_json::to_writer(
+                &mut writer,
+                &JsonRange {
+                    ip_from,
+                    ip_to,
+                    row,
+                },
+            )?;
+            writer.write_all(b"\n")
+        })?;
+
+        writer.flush()
+    }
+
+    /// Call `f` with each range of the selected tables, in ascending order.
+    ///
+    /// When visiting both tables, IPv4-mapped ranges of the IPv6 table are
+    /// replaced with the ranges from the IPv4 table, which are used for
+    /// lookups.
+    fn for_each_range<F>(&self, query: Columns, family: Family, mut f: F) -> io::Result<()>
+    where
+        F: FnMut(IpAddr, IpAddr, &Row) -> io::Result<()>,
+    {
+        let visit = |iter: Iter<'_, R>, f: &mut F| -> io::Result<()> {
+            for res in iter {
+                let (start, end, row) = res?;
+                f(start, end, &row)?;
+            }
+            Ok(())
+        };
+
+        match family {
+            Family::Ipv4 => visit(self.iter_ipv4(query), &mut f),
+            Family::Ipv6 => visit(self.iter_ipv6(query), &mut f),
+            Family::Both => {
+                let mut ipv4_visited = false;
+                for res in self.iter_ipv6(query) {
+                    let (start, end, row) = res?;
+                    let (start, end) = (u128::from(ipv6(start)), u128::from(ipv6(end)));
+                    if start < MAPPED_FROM {
+                        let end = end.min(MAPPED_FROM - 1);
+                        f(
+                            Ipv6Addr::from(start).into(),
+                            Ipv6Addr::from(end).into(),
+                            &row,
+                        )?;
+                    }
+                    if end >= MAPPED_FROM && !ipv4_visited {
+                        visit(self.iter_ipv4(query), &mut f)?;
+                        ipv4_visited = true;
+                    }
+                    if end > MAPPED_TO {
+                        let start = start.max(MAPPED_TO + 1);
+                        f(
+                            Ipv6Addr::from(start).into(),
+                            Ipv6Addr::from(end).into(),
+                            &row,
+                        )?;
+                    }
+                }
+                if !ipv4_visited {
+                    visit(self.iter_ipv4(query), &mut f)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn ipv6(addr: IpAddr) -> Ipv6Addr {
+    match addr {
+        IpAddr::V4(addr) => addr.to_ipv6_mapped(),
+        IpAddr::V6(addr) => addr,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "csv")]
+    #[test]
+    fn test_csv_roundtrip() {
+        let sample =
+            Database::open("data/IP2PROXY-IP-PROXYTYPE-COUNTRY-REGION-CITY-ISP.SAMPLE.BIN")
+                .unwrap();
+
+        for family in [Family::Ipv4, Family::Ipv6, Family::Both] {
+            let mut csv = Vec::new();
+            sample.export_csv(&mut csv, Columns::all(), family).unwrap();
+            let db = crate::DatabaseBuilder::from_csv(&csv[..])
+                .unwrap()
+                .build()
+                .unwrap();
+            assert_eq!(db.package_version(), sample.package_version());
+
+            let tables = match family {
+                Family::Ipv4 => vec![sample.iter_ipv4(Columns::all())],
+                Family::Ipv6 => vec![sample.iter_ipv6(Columns::all())],
+                Family::Both => vec![sample.iter_ipv4(Columns::all())],
+            };
+            for table in tables {
+                for res in table {
+                    let (start, end, row) = res.unwrap();
+                    assert_eq!(db.query(start, Columns::all()).unwrap(), Some(row.clone()));
+                    assert_eq!(db.query(end, Columns::all()).unwrap(), Some(row));
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_json_lines_both() {
+        let sample =
+            Database::open("data/IP2PROXY-IP-PROXYTYPE-COUNTRY-REGION-CITY-ISP.SAMPLE.BIN")
+                .unwrap();
+
+        let mut json = Vec::new();
+        sample
+            .export_json_lines(&mut json, Columns::PROXY_TYPE, Family::Both)
+            .unwrap();
+        let ranges: Vec<(IpAddr, IpAddr)> = std::str::from_utf8(&json)
+            .unwrap()
+            .lines()
+            .map(|line| {
+                let value: serde_json::Value = serde_json::from_str(line).unwrap();
+                (
+                    value["ip_from"].as_str().unwrap().parse().unwrap(),
+                    value["ip_to"].as_str().unwrap().parse().unwrap(),
+                )
+            })
+            .collect();
+
+        // Ascending and disjoint in the IPv6 layout.
+        let values: Vec<(u128, u128)> = ranges
+            .iter()
+            .map(|&(start, end)| (u128::from(ipv6(start)), u128::from(ipv6(end))))
+            .collect();
+        assert!(values.iter().all(|&(start, end)| start <= end));
+        assert!(values.windows(2).all(|w| w[0].1 < w[1].0));
+
+        // IPv4-mapped ranges come from the IPv4 table only.
+        assert!(
+            values
+                .iter()
+                .zip(&ranges)
+                .filter(|(_, (start, _))| start.is_ipv6())
+                .all(|(&(start, end), _)| end < MAPPED_FROM || start > MAPPED_TO)
+        );
+        let ipv4: Vec<(IpAddr, IpAddr)> = sample
+            .iter_ipv4(Columns::empty())
+            .map(|res| {
+                let (start, end, _) = res.unwrap();
+                (start, end)
+            })
+            .collect();
+        let exported: Vec<(IpAddr, IpAddr)> = ranges
+            .iter()
+            .copied()
+            .filter(|(start, _)| start.is_ipv4())
+            .collect();
+        assert_eq!(exported, ipv4);
+    }
+}
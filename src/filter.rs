@@ -0,0 +1,298 @@
+use std::{collections::HashMap, iter::FusedIterator, net::IpAddr};
+
+use crate::{
+    Columns, CountryCode, Database, Error, ProxyType, Row, Source, Threat, UsageType,
+    iter::RawRows, types,
+};
+
+/// Maximum number of memoized string comparisons of [`Find`], so that
+/// columns with many distinct values do not use unbounded memory.
+const MEMO_CAPACITY: usize = 1 << 12;
+
+/// Predicate on the fields of a [`Row`], used to search a database with
+/// [`Database::find()`].
+///
+/// All given conditions must match. A condition on a column that the
+/// database does not support never matches. Fields are compared by their
+/// typed values, like [`Row::parsed_proxy_type()`], so a malformed field
+/// never matches.
+///
+/// # Example
+///
+/// ```
+/// use ip2proxy::{Columns, CountryCode, Filter, ProxyType, Row};
+///
+/// let filter = Filter::new()
+///     .proxy_type(ProxyType::Tor)
+///     .country("DE".parse::<CountryCode>()?);
+///
+/// let mut row = Row::default();
+/// row.proxy_type = Some(String::from("TOR"));
+/// row.country_short = Some(String::from("DE"));
+/// assert!(filter.matches(&row));
+///
+/// assert_eq!(filter.columns(), Columns::PROXY_TYPE | Columns::COUNTRY_SHORT);
+/// # Ok::<_, Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct Filter {
+    conditions: Vec<Condition>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+enum Condition {
+    ProxyType(ProxyType),
+    Country(CountryCode),
+    UsageType(UsageType),
+    Asn(u32),
+    Threat(Threat),
+    Provider(String),
+}
+
+impl Condition {
+    fn column(&self) -> Columns {
+        match self {
+            Condition::ProxyType(_) => Columns::PROXY_TYPE,
+            Condition::Country(_) => Columns::COUNTRY_SHORT,
+            Condition::UsageType(_) => Columns::USAGE_TYPE,
+            Condition::Asn(_) => Columns::ASN,
+            Condition::Threat(_) => Columns::THREAT,
+            Condition::Provider(_) => Columns::PROVIDER,
+        }
+    }
+
+    fn matches(&self, field: &str) -> bool {
+        let field = Some(field);
+        match self {
+            Condition::ProxyType(proxy_type) => {
+                types::parse_proxy_type(field).as_ref() == Some(proxy_type)
+            }
+            Condition::Country(country) => types::parse_country(field) == Ok(Some(*country)),
+            Condition::UsageType(usage_type) => {
+                types::parse_codes::<UsageType>(field).contains(usage_type)
+            }
+            Condition::Asn(asn) => types::parse_number(Columns::ASN, field) == Ok(Some(*asn)),
+            Condition::Threat(threat) => types::parse_codes::<Threat>(field).contains(threat),
+            Condition::Provider(provider) => field == Some(provider.as_str()),
+        }
+    }
+}
+
+impl Filter {
+    /// Create a filter that matches every row.
+    pub fn new() -> Filter {
+        Filter::default()
+    }
+
+    fn with(mut self, condition: Condition) -> Filter {
+        self.conditions.push(condition);
+        self
+    }
+
+    /// Require [`Row::parsed_proxy_type()`] to be the given type.
+    pub fn proxy_type(self, proxy_type: ProxyType) -> Filter {
+        self.with(Condition::ProxyType(proxy_type))
+    }
+
+    /// Require [`Row::parsed_country()`] to be the given country.
+    pub fn country(self, country: CountryCode) -> Filter {
+        self.with(Condition::Country(country))
+    }
+
+    /// Require [`Row::parsed_usage_types()`] to include the given
+    /// classification.
+    pub fn usage_type(self, usage_type: UsageType) -> Filter {
+        self.with(Condition::UsageType(usage_type))
+    }
+
+    /// Require [`Row::parsed_asn()`] to be the given number.
+    pub fn asn(self, asn: u32) -> Filter {
+        self.with(Condition::Asn(asn))
+    }
+
+    /// Require [`Row::parsed_threats()`] to include the given threat.
+    pub fn threat(self, threat: Threat) -> Filter {
+        self.with(Condition::Threat(threat))
+    }
+
+    /// Require [`Row::provider`] to be equal to the given value.
+    pub fn provider(self, provider: impl Into<String>) -> Filter {
+        self.with(Condition::Provider(provider.into()))
+    }
+
+    /// Get the set of columns that the filter needs to inspect.
+    pub fn columns(&self) -> Columns {
+        self.conditions
+            .iter()
+            .fold(Columns::empty(), |acc, condition| acc | condition.column())
+    }
+
+    /// Checks if a row matches the filter.
+    pub fn matches(&self, row: &Row) -> bool {
+        self.conditions.iter().all(|condition| {
+            row.get(condition.column())
+                .is_some_and(|field| condition.matches(field))
+        })
+    }
+}
+
+impl<R: Source> Database<R> {
+    /// Find all ranges with rows matching a filter, in ascending order of
+    /// the IPv4 table followed by the IPv6 table.
+    ///
+    /// Only the strings needed to evaluate the filter are read for each
+    /// row, and recently compared strings are not read again. The full row
+    /// with the selected [`Columns`] is read only for matching ranges.
+    ///
+    /// Adjacent matching ranges are not merged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ip2proxy::{Columns, Database, Filter, ProxyType};
+    ///
+    /// let db = Database::open("data/IP2PROXY-IP-PROXYTYPE-COUNTRY-REGION-CITY-ISP.SAMPLE.BIN")?;
+    ///
+    /// let filter = Filter::new()
+    ///     .proxy_type(ProxyType::DataCenter)
+    ///     .country("AU".parse()?);
+    /// for res in db.find(filter, Columns::CITY) {
+    ///     let (start, end, row) = res?;
+    ///     println!("{start} - {end}: {:?}", row.city);
+    /// }
+    /// # Ok::<_, Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn find(&self, filter: Filter, query: Columns) -> Find<'_, R> {
+        Find {
+            raw: RawRows::new(self, self.table(false), self.table(true)),
+            filter,
+            query,
+            memo: HashMap::new(),
+        }
+    }
+}
+
+/// Iterator over ranges matching a [`Filter`].
+///
+/// See [`Database::find()`].
+#[derive(Debug)]
+pub struct Find<'a, R> {
+    raw: RawRows<'a, R>,
+    filter: Filter,
+    query: Columns,
+    /// Result of each condition, keyed by condition index and string
+    /// pointer.
+    memo: HashMap<(usize, u64), bool>,
+}
+
+impl<R: Source> Find<'_, R> {
+    fn matches(&mut self, cols: &[u8]) -> Result<bool, Error> {
+        let db = self.raw.db;
+        for (i, condition) in self.filter.conditions.iter().enumerate() {
+            let Some(ptr) = db.col_ptr(cols, condition.column())? else {
+                return Ok(false);
+            };
+            let matches = match self.memo.get(&(i, ptr)) {
+                Some(matches) => *matches,
+                None => {
+                    let matches = condition.matches(&db.read_str(ptr)?);
+                    if self.memo.len() >= MEMO_CAPACITY {
+                        self.memo.clear();
+                    }
+                    self.memo.insert((i, ptr), matches);
+                    matches
+                }
+            };
+            if !matches {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl<R: Source> Iterator for Find<'_, R> {
+    type Item = Result<(IpAddr, IpAddr, Row), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let res = self.raw.next()?.and_then(|raw| {
+                Ok(if self.matches(raw.cols())? {
+                    let range = raw.range();
+                    Some((
+                        *range.start(),
+                        *range.end(),
+                        self.raw.db.read_row(raw.cols(), self.query)?,
+                    ))
+                } else {
+                    None
+                })
+            });
+            match res {
+                Ok(Some(found)) => return Some(Ok(found)),
+                Ok(None) => continue,
+                Err(err) => {
+                    self.raw.stop();
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.raw.size_hint().1)
+    }
+}
+
+impl<R: Source> FusedIterator for Find<'_, R> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_matches_iter() {
+        let db = Database::open("data/IP2PROXY-IP-PROXYTYPE-COUNTRY-REGION-CITY-ISP.SAMPLE.BIN")
+            .unwrap();
+        let filter = Filter::new()
+            .proxy_type(ProxyType::DataCenter)
+            .country("AU".parse().unwrap());
+
+        let expected: Vec<_> = db
+            .iter(Columns::all())
+            .map(Result::unwrap)
+            .filter(|(_, _, row)| filter.matches(row))
+            .collect();
+        let found: Vec<_> = db
+            .find(filter, Columns::all())
+            .map(Result::unwrap)
+            .collect();
+
+        assert!(!found.is_empty());
+        assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn test_filter_typed() {
+        let mut row = Row {
+            usage_type: Some(String::from("ISP/MOB")),
+            asn: Some(String::from("13335")),
+            threat: Some(String::from("-")),
+            ..Row::default()
+        };
+
+        assert!(Filter::new().usage_type(UsageType::MobileIsp).matches(&row));
+        assert!(
+            !Filter::new()
+                .usage_type(UsageType::DataCenter)
+                .matches(&row)
+        );
+        assert!(Filter::new().asn(13335).matches(&row));
+        assert!(!Filter::new().asn(1333).matches(&row));
+        assert!(!Filter::new().threat(Threat::Spam).matches(&row));
+        assert!(!Filter::new().proxy_type(ProxyType::Vpn).matches(&row));
+
+        row.asn = Some(String::from("AS13335"));
+        assert!(!Filter::new().asn(13335).matches(&row));
+    }
+}
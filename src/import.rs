@@ -0,0 +1,194 @@
+use std::{io, net::IpAddr};
+
+use crate::{DatabaseBuilder, MAPPED_FROM, MAPPED_TO, Row};
+
+impl DatabaseBuilder {
+    /// Read ranges from an IP2Proxy CSV distribution (PX1 - PX11).
+    ///
+    /// The package version is inferred from the number of columns. PX9 and
+    /// PX10 have the same columns, so PX10 is chosen if residential proxies
+    /// (`RES`) are found, and PX9 otherwise.
+    ///
+    /// Both the IPv4 and IPv6 variants are supported, where addresses are
+    /// given as decimal numbers. IPv4-mapped IPv6 ranges are added to the
+    /// IPv4 table. A header line is skipped, if present.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ip2proxy::{Columns, DatabaseBuilder};
+    ///
+    /// let csv = concat!(
+    ///     "\"16777216\",\"16777471\",\"DCH\",\"AU\",\"Australia\"\n",
+    ///     "\"16777472\",\"16777727\",\"-\",\"-\",\"-\"\n",
+    /// );
+    ///
+    /// let mut builder = DatabaseBuilder::from_csv(csv.as_bytes())?;
+    /// builder.date(24, 5, 1);
+    ///
+    /// let db = builder.build()?;
+    /// assert_eq!(db.package_version(), 2);
+    /// let row = db.query("1.0.0.1".parse()?, Columns::all())?.unwrap();
+    /// assert_eq!(row.country_long, Some(String::from("Australia")));
+    /// # Ok::<_, Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * Error while reading.
+    /// * Malformed CSV, unsupported number of columns, or invalid
+    ///   addresses.
+    /// * Invalid ranges, see [`DatabaseBuilder::push()`].
+    pub fn from_csv<R: io::Read>(reader: R) -> io::Result<DatabaseBuilder> {
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader(reader);
+
+        let mut builder: Option<DatabaseBuilder> = None;
+        let mut ranges = Vec::new();
+        let mut record = csv::StringRecord::new();
+        while reader.read_record(&mut record)? {
+            let builder = match builder {
+                Some(ref mut builder) => builder,
+                None => {
+                    let px = csv_px(record.len()).ok_or_else(|| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("unsupported number of csv columns: {}", record.len()),
+                        )
+                    })?;
+                    let builder = builder.insert(DatabaseBuilder::new(px)?);
+                    if record[0].parse::<u128>().is_err() {
+                        continue; // Header
+                    }
+                    builder
+                }
+            };
+
+            let mut row = Row::default();
+            for (column, value) in builder.columns.iter().zip(record.iter().skip(2)) {
+                row.set(column, Some(value.to_owned()));
+            }
+
+            if builder.px == 9 && row.proxy_type.as_deref() == Some("RES") {
+                builder.px = 10;
+            }
+
+            let (start, end) = (csv_addr(&record[0])?, csv_addr(&record[1])?);
+            if start > end {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "range start after range end in csv",
+                ));
+            }
+            ranges.push((start, end, row));
+        }
+
+        let mut builder =
+            builder.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "empty csv"))?;
+
+        // The IPv6 variant covers all IPv6 addresses, with IPv4 addresses as
+        // IPv4-mapped addresses.
+        let ipv6 = ranges.iter().any(|(_, end, _)| *end > u128::from(u32::MAX));
+
+        for (start, end, row) in ranges {
+            if !ipv6 || (MAPPED_FROM <= start && end <= MAPPED_TO) {
+                let (start, end) = (start as u32, end as u32); // strip IPv4-mapped prefix, if any
+                builder.push(IpAddr::V4(start.into()), IpAddr::V4(end.into()), row)?;
+            } else {
+                builder.push(IpAddr::V6(start.into()), IpAddr::V6(end.into()), row)?;
+            }
+        }
+
+        Ok(builder)
+    }
+}
+
+/// Infers the package version from the number of CSV columns.
+fn csv_px(len: usize) -> Option<u8> {
+    Some(match len {
+        4 => 1,
+        5 => 2,
+        7 => 3,
+        8 => 4,
+        9 => 5,
+        10 => 6,
+        12 => 7,
+        13 => 8,
+        14 => 9,
+        15 => 11,
+        _ => return None,
+    })
+}
+
+fn csv_addr(addr: &str) -> io::Result<u128> {
+    addr.parse().map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid decimal address in csv: {addr:?}"),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Columns;
+
+    #[test]
+    fn test_csv_ipv6() {
+        let csv = concat!(
+            "\"0\",\"281470681743359\",\"-\",\"-\",\"-\"\n",
+            "\"281470681743360\",\"281470698520575\",\"-\",\"-\",\"-\"\n",
+            "\"281470698520576\",\"281470698520831\",\"DCH\",\"AU\",\"Australia\"\n",
+            "\"281470698520832\",\"281474976710655\",\"-\",\"-\",\"-\"\n",
+            "\"281474976710656\",\"42540766411282592856903984951653826559\",\"-\",\"-\",\"-\"\n",
+            "\"42540766411282592856903984951653826560\",\"42540766411282592856903984951653826815\",\"VPN\",\"DE\",\"Germany\"\n",
+            "\"42540766411282592856903984951653826816\",\"340282366920938463463374607431768211455\",\"-\",\"-\",\"-\"\n",
+        );
+        let db = DatabaseBuilder::from_csv(csv.as_bytes())
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let row = db
+            .query("1.0.0.1".parse().unwrap(), Columns::all())
+            .unwrap()
+            .unwrap();
+        assert_eq!(row.proxy_type.as_deref(), Some("DCH"));
+
+        let row = db
+            .query("2001:db8::1".parse().unwrap(), Columns::all())
+            .unwrap()
+            .unwrap();
+        assert_eq!(row.proxy_type.as_deref(), Some("VPN"));
+    }
+
+    #[test]
+    fn test_csv_ipv6_low_range() {
+        // In the IPv6 variant, small numbers are IPv6 addresses in ::/96,
+        // not IPv4 addresses.
+        let csv = concat!(
+            "\"0\",\"255\",\"VPN\",\"DE\",\"Germany\"\n",
+            "\"256\",\"281470681743359\",\"-\",\"-\",\"-\"\n",
+            "\"281470681743360\",\"281474976710655\",\"DCH\",\"AU\",\"Australia\"\n",
+            "\"281474976710656\",\"340282366920938463463374607431768211455\",\"-\",\"-\",\"-\"\n",
+        );
+        let db = DatabaseBuilder::from_csv(csv.as_bytes())
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let row = db
+            .query("::5".parse().unwrap(), Columns::all())
+            .unwrap()
+            .unwrap();
+        assert_eq!(row.proxy_type.as_deref(), Some("VPN"));
+
+        let row = db
+            .query("0.0.0.5".parse().unwrap(), Columns::all())
+            .unwrap()
+            .unwrap();
+        assert_eq!(row.proxy_type.as_deref(), Some("DCH"));
+    }
+}
@@ -0,0 +1,428 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    path::Path,
+    sync::{Arc, PoisonError, RwLock},
+};
+
+use positioned_io::RandomAccessFile;
+
+use crate::{
+    Columns, CountryCode, Database, Error, InvalidField, ProxyType, Row, Source, Threat, UsageType,
+    impl_column_fields, types,
+};
+
+/// Database record for an IP address, with shared strings.
+///
+/// Like [`Row`], but the values are reference counted, so that rows with
+/// the same values share them. Use [`InternedDatabase::query()`] to
+/// obtain this from a database.
+///
+/// With the `serde` feature, it is serialized like [`Row`].
+#[non_exhaustive]
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SharedRow {
+    /// See [`Row::proxy_type`].
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub proxy_type: Option<Arc<str>>,
+
+    /// See [`Row::country_short`].
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub country_short: Option<Arc<str>>,
+
+    /// See [`Row::country_long`].
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub country_long: Option<Arc<str>>,
+
+    /// See [`Row::region`].
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub region: Option<Arc<str>>,
+
+    /// See [`Row::city`].
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub city: Option<Arc<str>>,
+
+    /// See [`Row::isp`].
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub isp: Option<Arc<str>>,
+
+    /// See [`Row::domain`].
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub domain: Option<Arc<str>>,
+
+    /// See [`Row::usage_type`].
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub usage_type: Option<Arc<str>>,
+
+    /// See [`Row::asn`].
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub asn: Option<Arc<str>>,
+
+    /// See [`Row::as_name`].
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub as_name: Option<Arc<str>>,
+
+    /// See [`Row::last_seen`].
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub last_seen: Option<Arc<str>>,
+
+    /// See [`Row::threat`].
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub threat: Option<Arc<str>>,
+
+    /// See [`Row::provider`].
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub provider: Option<Arc<str>>,
+}
+
+impl_column_fields!(SharedRow, Arc<str>);
+
+impl SharedRow {
+    /// Get the value of a single column.
+    ///
+    /// Returns `None` if the column is not set, or if `column` is not
+    /// exactly one column.
+    pub fn get(&self, column: Columns) -> Option<&str> {
+        self.field(column)?.as_deref()
+    }
+
+    fn set(&mut self, column: Columns, value: Option<Arc<str>>) {
+        if let Some(field) = self.field_mut(column) {
+            *field = value;
+        }
+    }
+
+    /// See [`Row::is_proxy()`].
+    pub fn is_proxy(&self) -> Option<bool> {
+        types::is_proxy(self.country_short.as_deref(), self.proxy_type.as_deref())
+    }
+
+    /// See [`Row::parsed_proxy_type()`].
+    pub fn parsed_proxy_type(&self) -> Option<ProxyType> {
+        types::parse_proxy_type(self.proxy_type.as_deref())
+    }
+
+    /// See [`Row::parsed_usage_types()`].
+    pub fn parsed_usage_types(&self) -> Vec<UsageType> {
+        types::parse_codes(self.usage_type.as_deref())
+    }
+
+    /// See [`Row::parsed_threats()`].
+    pub fn parsed_threats(&self) -> Vec<Threat> {
+        types::parse_codes(self.threat.as_deref())
+    }
+
+    /// See [`Row::parsed_country()`].
+    ///
+    /// # Errors
+    ///
+    /// The field is not empty or `-`, and not a valid country code.
+    pub fn parsed_country(&self) -> Result<Option<CountryCode>, InvalidField> {
+        types::parse_country(self.country_short.as_deref())
+    }
+
+    /// See [`Row::parsed_asn()`].
+    ///
+    /// # Errors
+    ///
+    /// The field is not empty or `-`, and not a valid number.
+    pub fn parsed_asn(&self) -> Result<Option<u32>, InvalidField> {
+        types::parse_number(Columns::ASN, self.asn.as_deref())
+    }
+
+    /// See [`Row::parsed_last_seen()`].
+    ///
+    /// # Errors
+    ///
+    /// The field is not empty or `-`, and not a valid number.
+    pub fn parsed_last_seen(&self) -> Result<Option<u32>, InvalidField> {
+        types::parse_number(Columns::LAST_SEEN, self.last_seen.as_deref())
+    }
+
+    /// Copy the values into a [`Row`].
+    pub fn to_row(&self) -> Row {
+        let mut row = Row::default();
+        for column in Columns::all().iter() {
+            row.set(column, self.get(column).map(ToOwned::to_owned));
+        }
+        row
+    }
+}
+
+impl From<&SharedRow> for Row {
+    fn from(row: &SharedRow) -> Row {
+        row.to_row()
+    }
+}
+
+impl From<SharedRow> for Row {
+    fn from(row: SharedRow) -> Row {
+        row.to_row()
+    }
+}
+
+/// An IP2Proxy BIN database that interns strings.
+///
+/// Values like country names, proxy types and ISP names repeat across many
+/// rows, where they are stored only once in the file. Each distinct string
+/// is decoded once and then shared by all [`SharedRow`]s that contain it,
+/// so repeated lookups do not allocate for the values.
+///
+/// Interned strings are kept until [`InternedDatabase::clear()`]. Strings
+/// are interned by their offset in the file, so without a limit, the
+/// number of interned strings grows up to the number of distinct strings
+/// in the file, and memory usage up to the size of its string pool. Use
+/// [`InternedDatabase::with_capacity()`] to limit the number of interned
+/// strings. Looking up a string that is not yet interned takes a write
+/// lock, until the limit is reached.
+///
+/// # Example
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use ip2proxy::{Columns, InternedDatabase};
+///
+/// let db = InternedDatabase::open("data/IP2PROXY-IP-PROXYTYPE-COUNTRY-REGION-CITY-ISP.SAMPLE.BIN")?;
+///
+/// let a = db.query("1.0.0.1".parse()?, Columns::COUNTRY_LONG)?.unwrap();
+/// let b = db.query("1.0.0.2".parse()?, Columns::COUNTRY_LONG)?.unwrap();
+/// assert_eq!(a.country_long.as_deref(), Some("Australia"));
+/// assert!(Arc::ptr_eq(&a.country_long.unwrap(), &b.country_long.unwrap()));
+/// # Ok::<_, Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+pub struct InternedDatabase<R = RandomAccessFile> {
+    db: Database<R>,
+    strings: RwLock<HashMap<u64, Arc<str>>>,
+    capacity: usize,
+}
+
+impl InternedDatabase {
+    /// Open a database file.
+    ///
+    /// # Errors
+    ///
+    /// See [`Database::open()`].
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Database::open(path).map(InternedDatabase::new)
+    }
+}
+
+impl<R> InternedDatabase<R> {
+    /// Wrap a database, interning all strings of the file.
+    pub fn new(db: Database<R>) -> InternedDatabase<R> {
+        InternedDatabase::with_capacity(db, usize::MAX)
+    }
+
+    /// Wrap a database, interning up to `capacity` strings. Once the limit
+    /// is reached, strings that are not yet interned are decoded for each
+    /// lookup.
+    pub fn with_capacity(db: Database<R>, capacity: usize) -> InternedDatabase<R> {
+        InternedDatabase {
+            db,
+            strings: RwLock::new(HashMap::new()),
+            capacity,
+        }
+    }
+
+    /// Get the underlying database.
+    pub fn database(&self) -> &Database<R> {
+        &self.db
+    }
+
+    /// Get the number of interned strings.
+    pub fn interned(&self) -> usize {
+        self.strings
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+
+    /// Release all interned strings. Rows that were already returned keep
+    /// their values.
+    pub fn clear(&self) {
+        self.strings
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+}
+
+impl<R: Source> InternedDatabase<R> {
+    /// Look up information for an IP address.
+    ///
+    /// See [`Database::query()`].
+    ///
+    /// # Errors
+    ///
+    /// * Error while reading from the source.
+    /// * Invalid row or string data.
+    pub fn query(&self, addr: IpAddr, query: Columns) -> Result<Option<SharedRow>, Error> {
+        let Some(raw) = self.db.find_row(addr)? else {
+            return Ok(None);
+        };
+        let mut row = SharedRow::default();
+        for column in (query & self.db.columns()).iter() {
+            if let Some(ptr) = self.db.col_ptr(raw.cols(), column)? {
+                row.set(column, Some(self.intern(ptr)?));
+            }
+        }
+        Ok(Some(row))
+    }
+
+    fn intern(&self, ptr: u64) -> Result<Arc<str>, Error> {
+        let full = {
+            let strings = self.strings.read().unwrap_or_else(PoisonError::into_inner);
+            if let Some(s) = strings.get(&ptr) {
+                return Ok(Arc::clone(s));
+            }
+            strings.len() >= self.capacity
+        };
+        let s = self.db.read_str_with(ptr, |s| Arc::<str>::from(s))?;
+        if full {
+            return Ok(s);
+        }
+
+        let mut strings = self.strings.write().unwrap_or_else(PoisonError::into_inner);
+        if let Some(s) = strings.get(&ptr) {
+            return Ok(Arc::clone(s));
+        }
+        if strings.len() < self.capacity {
+            strings.insert(ptr, Arc::clone(&s));
+        }
+        Ok(s)
+    }
+}
+
+impl<R> From<Database<R>> for InternedDatabase<R> {
+    fn from(db: Database<R>) -> InternedDatabase<R> {
+        InternedDatabase::new(db)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::SAMPLE;
+
+    #[test]
+    fn test_interned_rows() {
+        let db = InternedDatabase::open(SAMPLE).unwrap();
+        let addr = "1.0.0.1".parse().unwrap();
+
+        let row = db.database().query(addr, Columns::all()).unwrap().unwrap();
+        let shared = db.query(addr, Columns::all()).unwrap().unwrap();
+        assert_eq!(shared.to_row(), row);
+        assert_eq!(shared.is_proxy(), row.is_proxy());
+        assert_eq!(shared.parsed_proxy_type(), row.parsed_proxy_type());
+        assert_eq!(shared.parsed_country(), row.parsed_country());
+
+        // Only selected columns are interned.
+        db.clear();
+        let shared = db.query(addr, Columns::CITY).unwrap().unwrap();
+        assert_eq!(shared.get(Columns::CITY), Some("Brisbane"));
+        assert_eq!(shared.proxy_type, None);
+        assert_eq!(db.interned(), 1);
+
+        // Returned rows keep their values.
+        db.clear();
+        assert_eq!(db.interned(), 0);
+        assert_eq!(shared.city.as_deref(), Some("Brisbane"));
+    }
+
+    #[test]
+    fn test_interned_shared() {
+        let addr = "1.0.0.1".parse().unwrap();
+        let query = Columns::COUNTRY_SHORT | Columns::COUNTRY_LONG;
+
+        let db = InternedDatabase::open(SAMPLE).unwrap();
+        let a = db.query(addr, query).unwrap().unwrap();
+        let b = db.query(addr, query).unwrap().unwrap();
+        assert!(Arc::ptr_eq(
+            a.country_long.as_ref().unwrap(),
+            b.country_long.as_ref().unwrap()
+        ));
+        assert_eq!(db.interned(), 2);
+
+        // Other rows share the strings, by offset in the file.
+        let other = db
+            .query("223.252.0.1".parse().unwrap(), query)
+            .unwrap()
+            .unwrap();
+        assert!(Arc::ptr_eq(
+            a.country_short.as_ref().unwrap(),
+            other.country_short.as_ref().unwrap()
+        ));
+        assert_eq!(db.interned(), 2);
+
+        // Strings beyond the capacity are not shared.
+        let db = InternedDatabase::with_capacity(Database::open(SAMPLE).unwrap(), 1);
+        let a = db.query(addr, query).unwrap().unwrap();
+        let b = db.query(addr, query).unwrap().unwrap();
+        assert!(Arc::ptr_eq(
+            a.country_short.as_ref().unwrap(),
+            b.country_short.as_ref().unwrap()
+        ));
+        assert!(!Arc::ptr_eq(
+            a.country_long.as_ref().unwrap(),
+            b.country_long.as_ref().unwrap()
+        ));
+        assert_eq!(a, b);
+        assert_eq!(db.interned(), 1);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_shared_row_serde() {
+        let db = InternedDatabase::open(SAMPLE).unwrap();
+
+        let shared = db
+            .query("1.0.0.1".parse().unwrap(), Columns::all())
+            .unwrap()
+            .unwrap();
+        let json = serde_json::to_string(&shared).unwrap();
+        assert_eq!(json, serde_json::to_string(&shared.to_row()).unwrap());
+        assert_eq!(serde_json::from_str::<SharedRow>(&json).unwrap(), shared);
+    }
+}
@@ -0,0 +1,663 @@
+//! Support for **IP2Location BIN Data** files (DB1 - DB26), with
+//! geolocation information for IP address ranges.
+//!
+//! They share the file structure of IP2Proxy BIN files, with different
+//! sets of columns.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use ip2proxy::ip2location::{Columns, Database};
+//!
+//! let db = Database::open("IP2LOCATION-LITE-DB5.BIN")?;
+//!
+//! if let Some(row) = db.query("8.8.8.8".parse()?, Columns::all())? {
+//!     println!("{:?} {:?}", row.latitude, row.longitude);
+//! }
+//! # Ok::<_, Box<dyn std::error::Error>>(())
+//! ```
+
+use std::{fmt, net::IpAddr, path::Path};
+
+use bitflags::bitflags;
+use byteorder::{ByteOrder as _, LE};
+use positioned_io::RandomAccessFile;
+
+use crate::{Error, Header, MAX_BIN_COLUMNS, PRODUCT_IP2LOCATION, Source};
+
+bitflags! {
+    /// Set of supported or selected columns of an IP2Location database.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ip2proxy::ip2location::Columns;
+    ///
+    /// assert_eq!(Columns::DB5, Columns::DB3 | Columns::LATITUDE | Columns::LONGITUDE);
+    /// ```
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct Columns: u32 {
+        /// See [`Row::country_short`].
+        const COUNTRY_SHORT        = 1 <<  0;
+        /// See [`Row::country_long`].
+        const COUNTRY_LONG         = 1 <<  1;
+        /// See [`Row::region`].
+        const REGION               = 1 <<  2;
+        /// See [`Row::city`].
+        const CITY                 = 1 <<  3;
+        /// See [`Row::isp`].
+        const ISP                  = 1 <<  4;
+        /// See [`Row::latitude`].
+        const LATITUDE             = 1 <<  5;
+        /// See [`Row::longitude`].
+        const LONGITUDE            = 1 <<  6;
+        /// See [`Row::domain`].
+        const DOMAIN               = 1 <<  7;
+        /// See [`Row::zip_code`].
+        const ZIP_CODE             = 1 <<  8;
+        /// See [`Row::time_zone`].
+        const TIME_ZONE            = 1 <<  9;
+        /// See [`Row::net_speed`].
+        const NET_SPEED            = 1 << 10;
+        /// See [`Row::idd_code`].
+        const IDD_CODE             = 1 << 11;
+        /// See [`Row::area_code`].
+        const AREA_CODE            = 1 << 12;
+        /// See [`Row::weather_station_code`].
+        const WEATHER_STATION_CODE = 1 << 13;
+        /// See [`Row::weather_station_name`].
+        const WEATHER_STATION_NAME = 1 << 14;
+        /// See [`Row::mcc`].
+        const MCC                  = 1 << 15;
+        /// See [`Row::mnc`].
+        const MNC                  = 1 << 16;
+        /// See [`Row::mobile_brand`].
+        const MOBILE_BRAND         = 1 << 17;
+        /// See [`Row::elevation`].
+        const ELEVATION            = 1 << 18;
+        /// See [`Row::usage_type`].
+        const USAGE_TYPE           = 1 << 19;
+        /// See [`Row::address_type`].
+        const ADDRESS_TYPE         = 1 << 20;
+        /// See [`Row::category`].
+        const CATEGORY             = 1 << 21;
+        /// See [`Row::district`].
+        const DISTRICT             = 1 << 22;
+        /// See [`Row::asn`].
+        const ASN                  = 1 << 23;
+        /// See [`Row::as_name`].
+        const AS_NAME              = 1 << 24;
+
+        /// Alias for columns of DB1: IP-Country.
+        const DB1 = Columns::COUNTRY_SHORT.bits() | Columns::COUNTRY_LONG.bits();
+        /// Alias for columns of DB2: IP-Country-ISP.
+        const DB2 = Columns::DB1.bits() | Columns::ISP.bits();
+        /// Alias for columns of DB3: IP-Country-Region-City.
+        const DB3 = Columns::DB1.bits() | Columns::REGION.bits() | Columns::CITY.bits();
+        /// Alias for columns of DB4: IP-Country-Region-City-ISP.
+        const DB4 = Columns::DB3.bits() | Columns::ISP.bits();
+        /// Alias for columns of DB5: IP-Country-Region-City-Latitude-Longitude.
+        const DB5 = Columns::DB3.bits() | Columns::LATITUDE.bits() | Columns::LONGITUDE.bits();
+        /// Alias for columns of DB6: IP-Country-Region-City-Latitude-Longitude-ISP.
+        const DB6 = Columns::DB5.bits() | Columns::ISP.bits();
+        /// Alias for columns of DB7: IP-Country-Region-City-ISP-Domain.
+        const DB7 = Columns::DB4.bits() | Columns::DOMAIN.bits();
+        /// Alias for columns of DB8: IP-Country-Region-City-Latitude-Longitude-ISP-Domain.
+        const DB8 = Columns::DB6.bits() | Columns::DOMAIN.bits();
+        /// Alias for columns of DB9: IP-Country-Region-City-Latitude-Longitude-ZIPCode.
+        const DB9 = Columns::DB5.bits() | Columns::ZIP_CODE.bits();
+        /// Alias for columns of DB10: IP-Country-Region-City-Latitude-Longitude-ZIPCode-ISP-Domain.
+        const DB10 = Columns::DB8.bits() | Columns::ZIP_CODE.bits();
+        /// Alias for columns of DB11: IP-Country-Region-City-Latitude-Longitude-ZIPCode-TimeZone.
+        const DB11 = Columns::DB9.bits() | Columns::TIME_ZONE.bits();
+        /// Alias for columns of DB12: IP-Country-Region-City-Latitude-Longitude-ZIPCode-TimeZone-ISP-Domain.
+        const DB12 = Columns::DB10.bits() | Columns::TIME_ZONE.bits();
+        /// Alias for columns of DB13: IP-Country-Region-City-Latitude-Longitude-TimeZone-NetSpeed.
+        const DB13 = Columns::DB5.bits() | Columns::TIME_ZONE.bits() | Columns::NET_SPEED.bits();
+        /// Alias for columns of DB14: IP-Country-Region-City-Latitude-Longitude-ZIPCode-TimeZone-ISP-Domain-NetSpeed.
+        const DB14 = Columns::DB12.bits() | Columns::NET_SPEED.bits();
+        /// Alias for columns of DB15: IP-Country-Region-City-Latitude-Longitude-ZIPCode-TimeZone-AreaCode.
+        const DB15 = Columns::DB11.bits() | Columns::IDD_CODE.bits() | Columns::AREA_CODE.bits();
+        /// Alias for columns of DB16: IP-Country-Region-City-Latitude-Longitude-ZIPCode-TimeZone-ISP-Domain-NetSpeed-AreaCode.
+        const DB16 = Columns::DB14.bits() | Columns::IDD_CODE.bits() | Columns::AREA_CODE.bits();
+        /// Alias for columns of DB17: IP-Country-Region-City-Latitude-Longitude-TimeZone-NetSpeed-Weather.
+        const DB17 = Columns::DB13.bits() | Columns::WEATHER_STATION_CODE.bits() | Columns::WEATHER_STATION_NAME.bits();
+        /// Alias for columns of DB18: IP-Country-Region-City-Latitude-Longitude-ZIPCode-TimeZone-ISP-Domain-NetSpeed-AreaCode-Weather.
+        const DB18 = Columns::DB16.bits() | Columns::WEATHER_STATION_CODE.bits() | Columns::WEATHER_STATION_NAME.bits();
+        /// Alias for columns of DB19: IP-Country-Region-City-Latitude-Longitude-ISP-Domain-Mobile.
+        const DB19 = Columns::DB8.bits() | Columns::MCC.bits() | Columns::MNC.bits() | Columns::MOBILE_BRAND.bits();
+        /// Alias for columns of DB20: IP-Country-Region-City-Latitude-Longitude-ZIPCode-TimeZone-ISP-Domain-NetSpeed-AreaCode-Weather-Mobile.
+        const DB20 = Columns::DB18.bits() | Columns::MCC.bits() | Columns::MNC.bits() | Columns::MOBILE_BRAND.bits();
+        /// Alias for columns of DB21: IP-Country-Region-City-Latitude-Longitude-ZIPCode-TimeZone-AreaCode-Elevation.
+        const DB21 = Columns::DB15.bits() | Columns::ELEVATION.bits();
+        /// Alias for columns of DB22: IP-Country-Region-City-Latitude-Longitude-ZIPCode-TimeZone-ISP-Domain-NetSpeed-AreaCode-Weather-Mobile-Elevation.
+        const DB22 = Columns::DB20.bits() | Columns::ELEVATION.bits();
+        /// Alias for columns of DB23: IP-Country-Region-City-Latitude-Longitude-ISP-Domain-Mobile-UsageType.
+        const DB23 = Columns::DB19.bits() | Columns::USAGE_TYPE.bits();
+        /// Alias for columns of DB24: IP-Country-Region-City-Latitude-Longitude-ZIPCode-TimeZone-ISP-Domain-NetSpeed-AreaCode-Weather-Mobile-Elevation-UsageType.
+        const DB24 = Columns::DB22.bits() | Columns::USAGE_TYPE.bits();
+        /// Alias for columns of DB25: DB24 with AddressType-Category.
+        const DB25 = Columns::DB24.bits() | Columns::ADDRESS_TYPE.bits() | Columns::CATEGORY.bits();
+        /// Alias for columns of DB26: DB25 with District-ASN.
+        const DB26 = Columns::DB25.bits() | Columns::DISTRICT.bits() | Columns::ASN.bits() | Columns::AS_NAME.bits();
+    }
+}
+
+/// Position of each column in a row, by database type. Position 1 is the
+/// start address, 0 means the column is not supported.
+#[rustfmt::skip]
+const POSITIONS: [(Columns, [u8; 27]); 25] = [
+    (Columns::COUNTRY_SHORT,        [0, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2]),
+    (Columns::COUNTRY_LONG,         [0, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2]),
+    (Columns::REGION,               [0, 0, 0, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3]),
+    (Columns::CITY,                 [0, 0, 0, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4]),
+    (Columns::ISP,                  [0, 0, 3, 0, 5, 0, 7, 5, 7, 0, 8, 0, 9, 0, 9, 0, 9, 0, 9, 7, 9, 0, 9, 7, 9, 9, 9]),
+    (Columns::LATITUDE,             [0, 0, 0, 0, 0, 5, 5, 0, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5]),
+    (Columns::LONGITUDE,            [0, 0, 0, 0, 0, 6, 6, 0, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6]),
+    (Columns::DOMAIN,               [0, 0, 0, 0, 0, 0, 0, 6, 8, 0, 9, 0, 10, 0, 10, 0, 10, 0, 10, 8, 10, 0, 10, 8, 10, 10, 10]),
+    (Columns::ZIP_CODE,             [0, 0, 0, 0, 0, 0, 0, 0, 0, 7, 7, 7, 7, 0, 7, 7, 7, 0, 7, 0, 7, 7, 7, 0, 7, 7, 7]),
+    (Columns::TIME_ZONE,            [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 8, 8, 7, 8, 8, 8, 7, 8, 0, 8, 8, 8, 0, 8, 8, 8]),
+    (Columns::NET_SPEED,            [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 8, 11, 0, 11, 8, 11, 0, 11, 0, 11, 0, 11, 11, 11]),
+    (Columns::IDD_CODE,             [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 9, 12, 0, 12, 0, 12, 9, 12, 0, 12, 12, 12]),
+    (Columns::AREA_CODE,            [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 10, 13, 0, 13, 0, 13, 10, 13, 0, 13, 13, 13]),
+    (Columns::WEATHER_STATION_CODE, [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 9, 14, 0, 14, 0, 14, 0, 14, 14, 14]),
+    (Columns::WEATHER_STATION_NAME, [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 10, 15, 0, 15, 0, 15, 0, 15, 15, 15]),
+    (Columns::MCC,                  [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 9, 16, 0, 16, 9, 16, 16, 16]),
+    (Columns::MNC,                  [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 10, 17, 0, 17, 10, 17, 17, 17]),
+    (Columns::MOBILE_BRAND,         [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 11, 18, 0, 18, 11, 18, 18, 18]),
+    (Columns::ELEVATION,            [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 11, 19, 0, 19, 19, 19]),
+    (Columns::USAGE_TYPE,           [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 12, 20, 20, 20]),
+    (Columns::ADDRESS_TYPE,         [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 21, 21]),
+    (Columns::CATEGORY,             [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 22, 22]),
+    (Columns::DISTRICT,             [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 23]),
+    (Columns::ASN,                  [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 24]),
+    (Columns::AS_NAME,              [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 25]),
+];
+
+/// Get the columns of a database type, if supported.
+fn db_columns(db_type: u8) -> Option<Columns> {
+    let db_type = usize::from(db_type);
+    (1..=26).contains(&db_type).then(|| {
+        POSITIONS
+            .iter()
+            .filter(|(_, positions)| positions[db_type] != 0)
+            .fold(Columns::empty(), |acc, (column, _)| acc | *column)
+    })
+}
+
+/// IP2Location database record for an IP address.
+///
+/// Use [`Database::query()`] to obtain this from a database.
+///
+/// By convention, `-` is used for string fields where the column is
+/// supported but the cell does not have a value.
+#[non_exhaustive]
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Row {
+    /// ISO 3166 country code like `US`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub country_short: Option<String>,
+    /// ISO 3166 country name like `United States of America`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub country_long: Option<String>,
+    /// Region or state name like `California`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub region: Option<String>,
+    /// City name like `Mountain View`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub city: Option<String>,
+    /// Internet service provider or company name, like `Google LLC`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub isp: Option<String>,
+    /// Latitude of the city, in degrees.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub latitude: Option<f32>,
+    /// Longitude of the city, in degrees.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub longitude: Option<f32>,
+    /// Domain name associated with the IP address, like `google.com`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub domain: Option<String>,
+    /// ZIP or postal code.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub zip_code: Option<String>,
+    /// UTC offset like `-07:00`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub time_zone: Option<String>,
+    /// Connection speed like `DSL`, `COMP` or `T1`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub net_speed: Option<String>,
+    /// International direct dialing code like `1`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub idd_code: Option<String>,
+    /// Telephone area code like `650`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub area_code: Option<String>,
+    /// Code of the nearest weather station, like `USCA0746`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub weather_station_code: Option<String>,
+    /// Name of the nearest weather station, like `Mountain View`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub weather_station_name: Option<String>,
+    /// Mobile country code.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub mcc: Option<String>,
+    /// Mobile network code.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub mnc: Option<String>,
+    /// Brand name of the mobile carrier.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub mobile_brand: Option<String>,
+    /// Average height above sea level of the city, in meters.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub elevation: Option<String>,
+    /// Usage type classification like `ISP` or `DCH`, see
+    /// [`crate::Row::usage_type`].
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub usage_type: Option<String>,
+    /// Address type like `U` (unicast), `A` (anycast), `M` (multicast) or
+    /// `B` (broadcast).
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub address_type: Option<String>,
+    /// IAB category like `IAB19-11`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub category: Option<String>,
+    /// District or county name.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub district: Option<String>,
+    /// Autonomous system number like `15169`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub asn: Option<String>,
+    /// Autonomous system name like `Google LLC`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub as_name: Option<String>,
+}
+
+/// An IP2Location BIN database (DB1 - DB26).
+pub struct Database<R = RandomAccessFile> {
+    inner: crate::Database<R>,
+    columns: Columns,
+}
+
+impl<R> fmt::Debug for Database<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Database")
+            .field("header", &self.inner.header)
+            .field("columns", &self.columns)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Database {
+    /// Open a database file.
+    ///
+    /// # Errors
+    ///
+    /// * Error while opening the file.
+    /// * Error while reading from the file.
+    /// * Invalid data in header section or index section.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::from_reader_with(RandomAccessFile::open(path)?, true)
+    }
+}
+
+impl Database<Vec<u8>> {
+    /// Load a database from bytes in memory.
+    ///
+    /// # Errors
+    ///
+    /// * Invalid data in header section or index section.
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, Error> {
+        Self::from_reader_with(bytes, true)
+    }
+}
+
+impl<R: Source> Database<R> {
+    /// Open a database from any positioned reader.
+    ///
+    /// # Errors
+    ///
+    /// * Error while reading from the source.
+    /// * Not an IP2Location database, according to the product code in the
+    ///   extended header ([`Error::UnexpectedProduct`]), or source shorter
+    ///   than the declared file size ([`Error::FileSizeMismatch`]).
+    ///   Trailing data is allowed.
+    /// * Unsupported database type ([`Error::UnsupportedPackage`]) or
+    ///   invalid number of columns ([`Error::InvalidColumnCount`]) in header
+    ///   section.
+    /// * Index section too short.
+    pub fn from_reader(reader: R) -> Result<Self, Error> {
+        Self::from_reader_with(reader, false)
+    }
+
+    fn from_reader_with(reader: R, whole_file: bool) -> Result<Self, Error> {
+        let mut columns = Columns::empty();
+        let inner = crate::Database::from_reader_with(reader, whole_file, |header: &Header| {
+            if let Some(extended) = header.extended {
+                extended.check_product(PRODUCT_IP2LOCATION)?;
+            }
+            columns = db_columns(header.px).ok_or(Error::UnsupportedPackage { px: header.px })?;
+            let needed = POSITIONS
+                .iter()
+                .map(|(_, positions)| positions[usize::from(header.px)])
+                .max()
+                .unwrap_or(0);
+            if header.num_columns < needed || MAX_BIN_COLUMNS < usize::from(header.num_columns) {
+                return Err(Error::InvalidColumnCount {
+                    num_columns: header.num_columns,
+                });
+            }
+            Ok(())
+        })?;
+        Ok(Database { inner, columns })
+    }
+
+    /// Look up information for an IP address.
+    ///
+    /// The [`Columns`] parameter allows optimizing the lookup by limiting
+    /// the number of columns to retrieve.
+    ///
+    /// # Errors
+    ///
+    /// * Error while reading from the source.
+    /// * Invalid row or string data.
+    pub fn query(&self, addr: IpAddr, query: Columns) -> Result<Option<Row>, Error> {
+        let Some(raw) = self.inner.find_row(addr)? else {
+            return Ok(None);
+        };
+        let cols = raw.cols();
+        let query = query & self.columns;
+        let db_type = usize::from(self.inner.header.px);
+
+        let mut row = Row::default();
+        for (column, positions) in POSITIONS {
+            if !query.contains(column) {
+                continue;
+            }
+            let offset = (usize::from(positions[db_type]) - 2) * 4; // position >= 2 for supported columns
+            let value = LE::read_u32(&cols[offset..]);
+            match column {
+                Columns::LATITUDE => row.latitude = Some(f32::from_bits(value)),
+                Columns::LONGITUDE => row.longitude = Some(f32::from_bits(value)),
+                column => {
+                    let ptr = if column == Columns::COUNTRY_LONG {
+                        u64::from(value) + 3
+                    } else {
+                        u64::from(value)
+                    };
+                    let value = Some(self.inner.read_str(ptr)?);
+                    match column {
+                        Columns::COUNTRY_SHORT => row.country_short = value,
+                        Columns::COUNTRY_LONG => row.country_long = value,
+                        Columns::REGION => row.region = value,
+                        Columns::CITY => row.city = value,
+                        Columns::ISP => row.isp = value,
+                        Columns::DOMAIN => row.domain = value,
+                        Columns::ZIP_CODE => row.zip_code = value,
+                        Columns::TIME_ZONE => row.time_zone = value,
+                        Columns::NET_SPEED => row.net_speed = value,
+                        Columns::IDD_CODE => row.idd_code = value,
+                        Columns::AREA_CODE => row.area_code = value,
+                        Columns::WEATHER_STATION_CODE => row.weather_station_code = value,
+                        Columns::WEATHER_STATION_NAME => row.weather_station_name = value,
+                        Columns::MCC => row.mcc = value,
+                        Columns::MNC => row.mnc = value,
+                        Columns::MOBILE_BRAND => row.mobile_brand = value,
+                        Columns::ELEVATION => row.elevation = value,
+                        Columns::USAGE_TYPE => row.usage_type = value,
+                        Columns::ADDRESS_TYPE => row.address_type = value,
+                        Columns::CATEGORY => row.category = value,
+                        Columns::DISTRICT => row.district = value,
+                        Columns::ASN => row.asn = value,
+                        _ => row.as_name = value,
+                    }
+                }
+            }
+        }
+        Ok(Some(row))
+    }
+}
+
+impl<R> Database<R> {
+    /// Get the database type, like `5` for DB5.
+    pub fn database_type(&self) -> u8 {
+        self.inner.header.px
+    }
+
+    /// Get database version as `YY.M.D`.
+    pub fn database_version(&self) -> String {
+        self.inner.database_version()
+    }
+
+    /// Get the set of supported columns.
+    pub fn columns(&self) -> Columns {
+        self.columns
+    }
+
+    /// Get the product code from the extended header of newer releases,
+    /// `1` for IP2Location.
+    pub fn product_code(&self) -> Option<u8> {
+        self.inner.product_code()
+    }
+
+    /// Get the license code from the extended header of newer releases.
+    pub fn license_code(&self) -> Option<u8> {
+        self.inner.license_code()
+    }
+
+    /// Get the file size in bytes from the extended header of newer
+    /// releases.
+    pub fn file_size(&self) -> Option<u32> {
+        self.inner.file_size()
+    }
+
+    /// Get the number of rows in the IPv4 table.
+    pub fn rows_ipv4(&self) -> u32 {
+        self.inner.rows_ipv4()
+    }
+
+    /// Get the number of rows in the IPv6 table.
+    pub fn rows_ipv6(&self) -> u32 {
+        self.inner.rows_ipv6()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use byteorder::WriteBytesExt as _;
+
+    use super::*;
+
+    #[test]
+    fn test_db_aliases() {
+        for db_type in 1..=26 {
+            let alias = Columns::from_name(&format!("DB{db_type}"));
+            assert!(alias.is_some(), "DB{db_type}");
+            assert_eq!(alias, db_columns(db_type), "DB{db_type}");
+        }
+        assert_eq!(db_columns(0), None);
+        assert_eq!(db_columns(27), None);
+    }
+
+    /// Build a minimal DB5 file with an IPv4 table only.
+    fn db5(db_type: u8, num_columns: u8) -> Vec<u8> {
+        let starts = [0, 0x0100_0000, 0x0100_0100, u32::MAX];
+        let row_len = usize::from(num_columns) * 4;
+        let index_ptr = 64 + 1;
+        let base_ptr = index_ptr + (1 << 16) * 8;
+        let strings = base_ptr - 1 + starts.len() * row_len;
+
+        // Dash in country format at 0, other strings following.
+        let mut pool = Vec::new();
+        pool.extend_from_slice(b"\x01-\x00\x01-");
+        let us = pool.len();
+        pool.extend_from_slice(b"\x02US\x0dUnited States");
+        let region = pool.len();
+        pool.extend_from_slice(b"\x0aCalifornia");
+        let city = pool.len();
+        pool.extend_from_slice(b"\x0dMountain View");
+
+        let mut buf = Vec::new();
+        buf.push(db_type);
+        buf.push(num_columns);
+        buf.extend_from_slice(&[24, 5, 1]);
+        for n in [
+            starts.len() as u32,
+            base_ptr as u32,
+            0,
+            0,
+            index_ptr as u32,
+            0,
+        ] {
+            buf.write_u32::<LE>(n).unwrap();
+        }
+        buf.resize(64, 0);
+
+        let row_containing = |addr: u32| starts.partition_point(|start| *start <= addr) as u32 - 1;
+        for upper in 0..(1 << 16) {
+            buf.write_u32::<LE>(row_containing(upper << 16)).unwrap();
+            buf.write_u32::<LE>(row_containing((upper << 16) | 0xffff))
+                .unwrap();
+        }
+
+        for start in starts {
+            let row_start = buf.len();
+            buf.write_u32::<LE>(start).unwrap();
+            if start == 0x0100_0000 {
+                for ptr in [us, region, city] {
+                    buf.write_u32::<LE>((strings + ptr) as u32).unwrap();
+                }
+                buf.write_f32::<LE>(37.386).unwrap();
+                buf.write_f32::<LE>(-122.0838).unwrap();
+            } else {
+                for _ in 0..3 {
+                    buf.write_u32::<LE>(strings as u32).unwrap();
+                }
+            }
+            buf.resize(row_start + row_len, 0);
+        }
+        assert_eq!(buf.len(), strings);
+        buf.extend_from_slice(&pool);
+        buf
+    }
+
+    #[test]
+    fn test_db5() {
+        let db = Database::from_bytes(db5(5, 6)).unwrap();
+        assert_eq!(db.database_type(), 5);
+        assert_eq!(db.database_version(), "24.5.1");
+        assert_eq!(db.columns(), Columns::DB5);
+
+        let row = db
+            .query("1.0.0.1".parse().unwrap(), Columns::all())
+            .unwrap()
+            .unwrap();
+        assert_eq!(row.country_short.as_deref(), Some("US"));
+        assert_eq!(row.country_long.as_deref(), Some("United States"));
+        assert_eq!(row.region.as_deref(), Some("California"));
+        assert_eq!(row.city.as_deref(), Some("Mountain View"));
+        assert_eq!(row.latitude, Some(37.386));
+        assert_eq!(row.longitude, Some(-122.0838));
+        assert_eq!(row.isp, None);
+
+        let row = db
+            .query("1.0.1.1".parse().unwrap(), Columns::CITY | Columns::ISP)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            row,
+            Row {
+                city: Some(String::from("-")),
+                ..Row::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_invalid_header() {
+        assert!(matches!(
+            Database::from_bytes(db5(27, 6)),
+            Err(Error::UnsupportedPackage { px: 27 })
+        ));
+        assert!(matches!(
+            Database::from_bytes(db5(5, 5)),
+            Err(Error::InvalidColumnCount { num_columns: 5 })
+        ));
+    }
+}
@@ -0,0 +1,199 @@
+use std::{iter::FusedIterator, net::IpAddr};
+
+use crate::{Columns, Database, Error, RawRow, Row, Source, Table};
+
+impl<R: Source> Database<R> {
+    /// Iterate over all rows of the IPv4 table, in ascending order.
+    ///
+    /// Yields the first address, the last address (inclusive) and the
+    /// [`Row`] of each range. The [`Columns`] parameter selects the columns
+    /// to retrieve, like for [`Database::query()`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ip2proxy::{Columns, Database};
+    ///
+    /// let db = Database::open("data/IP2PROXY-IP-PROXYTYPE-COUNTRY-REGION-CITY-ISP.SAMPLE.BIN")?;
+    ///
+    /// for res in db.iter_ipv4(Columns::PROXY_TYPE) {
+    ///     let (start, end, row) = res?;
+    ///     assert!(start <= end);
+    /// }
+    /// # Ok::<_, Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn iter_ipv4(&self, query: Columns) -> Iter<'_, R> {
+        Iter::new(self, query, self.table(false), None)
+    }
+
+    /// Iterate over all rows of the IPv6 table, in ascending order.
+    ///
+    /// See [`Database::iter_ipv4()`].
+    pub fn iter_ipv6(&self, query: Columns) -> Iter<'_, R> {
+        Iter::new(self, query, self.table(true), None)
+    }
+
+    /// Iterate over all rows of the IPv4 table, followed by all rows of the
+    /// IPv6 table.
+    ///
+    /// See [`Database::iter_ipv4()`].
+    pub fn iter(&self, query: Columns) -> Iter<'_, R> {
+        Iter::new(self, query, self.table(false), self.table(true))
+    }
+}
+
+/// Selects the IPv4 table, the IPv6 table, or both.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub enum Family {
+    /// IPv4 table only.
+    Ipv4,
+    /// IPv6 table only.
+    Ipv6,
+    /// IPv4 and IPv6 tables.
+    #[default]
+    Both,
+}
+
+/// Iterator over the rows of a database.
+///
+/// See [`Database::iter()`].
+#[derive(Debug)]
+pub struct Iter<'a, R> {
+    raw: RawRows<'a, R>,
+    query: Columns,
+}
+
+impl<'a, R> Iter<'a, R> {
+    fn new(
+        db: &'a Database<R>,
+        query: Columns,
+        table: Option<Table>,
+        next_table: Option<Table>,
+    ) -> Iter<'a, R> {
+        Iter {
+            raw: RawRows::new(db, table, next_table),
+            query,
+        }
+    }
+}
+
+impl<R: Source> Iterator for Iter<'_, R> {
+    type Item = Result<(IpAddr, IpAddr, Row), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let res = self.raw.next()?.and_then(|raw| {
+            let range = raw.range();
+            Ok((
+                *range.start(),
+                *range.end(),
+                self.raw.db.read_row(raw.cols(), self.query)?,
+            ))
+        });
+        if res.is_err() {
+            self.raw.stop();
+        }
+        Some(res)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.raw.size_hint()
+    }
+}
+
+impl<R: Source> FusedIterator for Iter<'_, R> {}
+
+/// Iterator over the raw rows of one or two tables, before reading any
+/// strings.
+#[derive(Debug)]
+pub(crate) struct RawRows<'a, R> {
+    pub(crate) db: &'a Database<R>,
+    table: Option<Table>,
+    next_table: Option<Table>,
+    index: u32,
+}
+
+impl<'a, R> RawRows<'a, R> {
+    pub(crate) fn new(
+        db: &'a Database<R>,
+        table: Option<Table>,
+        next_table: Option<Table>,
+    ) -> RawRows<'a, R> {
+        RawRows {
+            db,
+            table,
+            next_table,
+            index: 0,
+        }
+    }
+
+    pub(crate) fn stop(&mut self) {
+        self.table = None;
+        self.next_table = None;
+    }
+}
+
+impl<R: Source> Iterator for RawRows<'_, R> {
+    type Item = Result<RawRow, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let table = self.table?;
+
+            // The last row of each table only marks the end of the previous
+            // range.
+            if self.index.saturating_add(1) >= table.rows {
+                self.table = self.next_table.take();
+                self.index = 0;
+                continue;
+            }
+
+            let res = self.db.read_raw_row(&table, self.index);
+            self.index += 1;
+            if res.is_err() {
+                self.stop();
+            }
+            return Some(res);
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = |table: Option<Table>, index: u32| {
+            table.map_or(0, |t| {
+                t.rows.saturating_sub(1).saturating_sub(index) as usize
+            })
+        };
+        let len = remaining(self.table, self.index) + remaining(self.next_table, 0);
+        (0, Some(len))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    #[test]
+    fn test_iter_ipv4_contiguous() {
+        let db = Database::open("data/IP2PROXY-IP-PROXYTYPE-COUNTRY-REGION-CITY-ISP.SAMPLE.BIN")
+            .unwrap();
+
+        let mut expected_start = Some(0);
+        for res in db.iter_ipv4(Columns::all()) {
+            let (start, end, row) = res.unwrap();
+            assert_eq!(
+                Some(start),
+                expected_start.map(|s| IpAddr::V4(Ipv4Addr::from(s)))
+            );
+            assert_eq!(db.query(start, Columns::all()).unwrap(), Some(row.clone()));
+            assert_eq!(db.query(end, Columns::all()).unwrap(), Some(row));
+            let IpAddr::V4(end) = end else { panic!() };
+            expected_start = u32::from(end).checked_add(1);
+        }
+        assert_eq!(expected_start, None, "last range ends at 255.255.255.255");
+        assert_eq!(
+            db.iter_ipv4(Columns::all()).count() as u32,
+            db.rows_ipv4() - 1
+        );
+    }
+}
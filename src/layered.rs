@@ -0,0 +1,549 @@
+use std::{
+    collections::BTreeMap,
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    path::Path,
+};
+
+use positioned_io::RandomAccessFile;
+
+use crate::{Columns, Database, Error, Row, Source, normalize_ip};
+
+/// Named map of address ranges to partial rows, to be layered on top of a
+/// database with [`LayeredDatabase`].
+///
+/// Fields that are `None` fall through to the layers below.
+///
+/// # Example
+///
+/// ```
+/// use ip2proxy::{Overlay, Row};
+///
+/// let mut row = Row::default();
+/// row.proxy_type = Some(String::from("VPN"));
+/// row.provider = Some(String::from("Corporate VPN"));
+///
+/// let mut overlay = Overlay::new("corporate");
+/// overlay.insert("192.0.2.0".parse()?, "192.0.2.255".parse()?, row)?;
+/// assert_eq!(overlay.len(), 1);
+/// # Ok::<_, Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Overlay {
+    name: String,
+    ranges: BTreeMap<(bool, u128), (u128, Row)>,
+}
+
+impl Overlay {
+    /// Create an empty overlay.
+    pub fn new(name: impl Into<String>) -> Overlay {
+        Overlay {
+            name: name.into(),
+            ranges: BTreeMap::new(),
+        }
+    }
+
+    /// Get the name of the overlay.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the number of ranges.
+    pub fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    /// Checks if the overlay has no ranges.
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Add a range of addresses from `start` to `end` (inclusive).
+    ///
+    /// IPv4-mapped IPv6 ranges are stored as IPv4 ranges.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`io::ErrorKind::InvalidInput`] if `start` and `end` are
+    /// of different address families, if `start` is after `end`, or if the
+    /// range overlaps a previous range of the overlay. Use separate
+    /// overlays for nested ranges.
+    pub fn insert(&mut self, start: IpAddr, end: IpAddr, row: Row) -> io::Result<()> {
+        let (start, end) = match (start, end) {
+            (IpAddr::V6(start), IpAddr::V6(end)) => {
+                match (start.to_ipv4_mapped(), end.to_ipv4_mapped()) {
+                    (Some(start), Some(end)) => (IpAddr::V4(start), IpAddr::V4(end)),
+                    _ => (IpAddr::V6(start), IpAddr::V6(end)),
+                }
+            }
+            range => range,
+        };
+        let (ipv6, start, end) = match (start, end) {
+            (IpAddr::V4(start), IpAddr::V4(end)) => (
+                false,
+                u128::from(u32::from(start)),
+                u128::from(u32::from(end)),
+            ),
+            (IpAddr::V6(start), IpAddr::V6(end)) => (true, u128::from(start), u128::from(end)),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "start and end of range have different address families",
+                ));
+            }
+        };
+        if start > end {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "start of range is after end",
+            ));
+        }
+        let overlaps_prev = self.find(ipv6, start).is_some();
+        let overlaps_next = self
+            .ranges
+            .range((ipv6, start)..=(ipv6, end))
+            .next()
+            .is_some();
+        if overlaps_prev || overlaps_next {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "range overlaps a previous range of the overlay",
+            ));
+        }
+        self.ranges.insert((ipv6, start), (end, row));
+        Ok(())
+    }
+
+    /// Look up the row for an address, if any.
+    ///
+    /// Like [`Database::query()`], 6to4 and Teredo addresses also match the
+    /// IPv4 address they embed, but ranges inserted for the IPv6 address
+    /// itself take precedence.
+    pub fn get(&self, addr: IpAddr) -> Option<&Row> {
+        self.find_addr(addr).or_else(|| {
+            let normalized = normalize_ip(addr);
+            (normalized != addr)
+                .then(|| self.find_addr(normalized))
+                .flatten()
+        })
+    }
+
+    fn find_addr(&self, addr: IpAddr) -> Option<&Row> {
+        match addr {
+            IpAddr::V4(addr) => self.find(false, u128::from(u32::from(addr))),
+            IpAddr::V6(addr) => match addr.to_ipv4_mapped() {
+                Some(addr) => self.find(false, u128::from(u32::from(addr))),
+                None => self.find(true, u128::from(addr)),
+            },
+        }
+    }
+
+    fn find(&self, ipv6: bool, addr: u128) -> Option<&Row> {
+        self.ranges
+            .range((ipv6, 0)..=(ipv6, addr))
+            .next_back()
+            .filter(|(_, (end, _))| addr <= *end)
+            .map(|(_, (_, row))| row)
+    }
+
+    /// Iterate over all ranges, in ascending order of IPv4 ranges followed
+    /// by IPv6 ranges.
+    pub fn iter(&self) -> impl Iterator<Item = (IpAddr, IpAddr, &Row)> + '_ {
+        self.ranges.iter().map(|(&(ipv6, start), (end, row))| {
+            if ipv6 {
+                (
+                    IpAddr::V6(Ipv6Addr::from(start)),
+                    IpAddr::V6(Ipv6Addr::from(*end)),
+                    row,
+                )
+            } else {
+                (
+                    IpAddr::V4(Ipv4Addr::from(start as u32)), // start <= u32::MAX
+                    IpAddr::V4(Ipv4Addr::from(*end as u32)),  // end <= u32::MAX
+                    row,
+                )
+            }
+        })
+    }
+}
+
+impl Overlay {
+    /// Read an overlay from CSV with a header line.
+    ///
+    /// The columns `ip_from` and `ip_to` hold the first and last address
+    /// of each range. All other columns are named like the fields of
+    /// [`Row`], for example `proxy_type`. Empty cells are `None`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ip2proxy::Overlay;
+    ///
+    /// let csv = concat!(
+    ///     "ip_from,ip_to,proxy_type,provider\n",
+    ///     "192.0.2.0,192.0.2.255,VPN,Corporate VPN\n",
+    ///     "2001:db8::,2001:db8::ffff,,Partner\n",
+    /// );
+    ///
+    /// let overlay = Overlay::from_csv("corporate", csv.as_bytes())?;
+    /// let row = overlay.get("192.0.2.1".parse()?).unwrap();
+    /// assert_eq!(row.provider, Some(String::from("Corporate VPN")));
+    /// # Ok::<_, Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * Error while reading.
+    /// * Malformed CSV, unknown columns, or invalid addresses.
+    /// * Invalid ranges, see [`Overlay::insert()`].
+    #[cfg(feature = "csv")]
+    pub fn from_csv<R: io::Read>(name: impl Into<String>, reader: R) -> io::Result<Overlay> {
+        let mut reader = csv::Reader::from_reader(reader);
+
+        let mut columns = Vec::new();
+        let (mut ip_from, mut ip_to) = (None, None);
+        for (i, header) in reader.headers()?.iter().enumerate() {
+            match header.trim() {
+                "ip_from" => ip_from = Some(i),
+                "ip_to" => ip_to = Some(i),
+                header => columns.push((
+                    i,
+                    Columns::from_name(&header.to_ascii_uppercase()).ok_or_else(|| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("unknown csv column: {header:?}"),
+                        )
+                    })?,
+                )),
+            }
+        }
+        let (Some(ip_from), Some(ip_to)) = (ip_from, ip_to) else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "missing csv column ip_from or ip_to",
+            ));
+        };
+
+        let mut overlay = Overlay::new(name);
+        let mut record = csv::StringRecord::new();
+        while reader.read_record(&mut record)? {
+            let mut row = Row::default();
+            for (i, column) in &columns {
+                row.set(
+                    *column,
+                    record
+                        .get(*i)
+                        .filter(|value| !value.is_empty())
+                        .map(str::to_owned),
+                );
+            }
+            overlay.insert(csv_addr(&record, ip_from)?, csv_addr(&record, ip_to)?, row)?;
+        }
+        Ok(overlay)
+    }
+
+    /// Read an overlay from JSON Lines, with one object per range.
+    ///
+    /// Objects have the first and last address of the range as `ip_from`
+    /// and `ip_to`, and fields of [`Row`] in its `serde` representation,
+    /// like the output of
+    /// [`Database::export_json_lines()`](crate::Database::export_json_lines).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ip2proxy::Overlay;
+    ///
+    /// let json = r#"{"ip_from":"192.0.2.0","ip_to":"192.0.2.255","proxy_type":"VPN"}"#;
+    ///
+    /// let overlay = Overlay::from_json_lines("corporate", json.as_bytes())?;
+    /// let row = overlay.get("192.0.2.1".parse()?).unwrap();
+    /// assert_eq!(row.proxy_type, Some(String::from("VPN")));
+    /// # Ok::<_, Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * Error while reading.
+    /// * Malformed JSON.
+    /// * Invalid ranges, see [`Overlay::insert()`].
+    #[cfg(feature = "json")]
+    pub fn from_json_lines<R: io::BufRead>(
+        name: impl Into<String>,
+        reader: R,
+    ) -> io::Result<Overlay> {
+        #[derive(serde::Deserialize)]
+        struct JsonRange {
+            ip_from: IpAddr,
+            ip_to: IpAddr,
+            #[serde(flatten)]
+            row: Row,
+        }
+
+        let mut overlay = Overlay::new(name);
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let range: JsonRange = serde_json::from_str(&line)?;
+            overlay.insert(range.ip_from, range.ip_to, range.row)?;
+        }
+        Ok(overlay)
+    }
+}
+
+#[cfg(feature = "csv")]
+fn csv_addr(record: &csv::StringRecord, i: usize) -> io::Result<IpAddr> {
+    let addr = record.get(i).unwrap_or_default().trim();
+    addr.parse().map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid ip address in csv: {addr:?}"),
+        )
+    })
+}
+
+/// Layer that provided a field of a [`LayeredRow`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Layer {
+    /// Overlay at the given index of [`LayeredDatabase::overlays()`].
+    Overlay(usize),
+    /// The underlying database.
+    Database,
+}
+
+/// Result of [`LayeredDatabase::query()`].
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct LayeredRow {
+    /// Fields merged from all layers.
+    pub row: Row,
+    sources: [Option<Layer>; 13],
+}
+
+impl LayeredRow {
+    /// Get the layer that provided the value of `column`, if any.
+    pub fn source(&self, column: Columns) -> Option<Layer> {
+        column_index(column).and_then(|i| self.sources[i])
+    }
+
+    fn fill(&mut self, layer: Layer, row: &Row, missing: &mut Columns) {
+        for column in missing.iter() {
+            if let Some(value) = row.get(column) {
+                self.row.set(column, Some(value.to_owned()));
+                if let Some(i) = column_index(column) {
+                    self.sources[i] = Some(layer);
+                }
+                missing.remove(column);
+            }
+        }
+    }
+}
+
+fn column_index(column: Columns) -> Option<usize> {
+    (column.bits().count_ones() == 1).then(|| column.bits().trailing_zeros() as usize)
+}
+
+/// A database with overlays that take precedence over its rows, field by
+/// field.
+///
+/// Overlays are consulted from the most recently pushed to the first, and
+/// finally the database. Each field of the result is taken from the first
+/// layer that has a value for it.
+///
+/// # Example
+///
+/// ```
+/// use ip2proxy::{Columns, Database, Layer, LayeredDatabase, Overlay, Row};
+///
+/// let db = Database::open("data/IP2PROXY-IP-PROXYTYPE-COUNTRY-REGION-CITY-ISP.SAMPLE.BIN")?;
+///
+/// let mut row = Row::default();
+/// row.proxy_type = Some(String::from("VPN"));
+/// let mut overlay = Overlay::new("partners");
+/// overlay.insert("1.0.0.0".parse()?, "1.0.0.255".parse()?, row)?;
+///
+/// let mut db = LayeredDatabase::new(db);
+/// db.push_overlay(overlay);
+///
+/// let lookup = db.query("1.0.0.1".parse()?, Columns::all())?.unwrap();
+/// assert_eq!(lookup.row.proxy_type, Some(String::from("VPN")));
+/// assert_eq!(lookup.source(Columns::PROXY_TYPE), Some(Layer::Overlay(0)));
+/// assert_eq!(lookup.row.country_short, Some(String::from("AU")));
+/// assert_eq!(lookup.source(Columns::COUNTRY_SHORT), Some(Layer::Database));
+/// # Ok::<_, Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+pub struct LayeredDatabase<R = RandomAccessFile> {
+    db: Database<R>,
+    overlays: Vec<Overlay>,
+}
+
+impl LayeredDatabase {
+    /// Open a database file, without any overlays.
+    ///
+    /// # Errors
+    ///
+    /// See [`Database::open()`].
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Database::open(path).map(LayeredDatabase::new)
+    }
+}
+
+impl<R> LayeredDatabase<R> {
+    /// Wrap a database, without any overlays.
+    pub fn new(db: Database<R>) -> LayeredDatabase<R> {
+        LayeredDatabase {
+            db,
+            overlays: Vec::new(),
+        }
+    }
+
+    /// Add an overlay on top of all previous layers.
+    pub fn push_overlay(&mut self, overlay: Overlay) -> &mut LayeredDatabase<R> {
+        self.overlays.push(overlay);
+        self
+    }
+
+    /// Get the overlays, from the bottom to the top.
+    pub fn overlays(&self) -> &[Overlay] {
+        &self.overlays
+    }
+
+    /// Get the underlying database.
+    pub fn database(&self) -> &Database<R> {
+        &self.db
+    }
+}
+
+impl<R: Source> LayeredDatabase<R> {
+    /// Look up information for an IP address in all layers.
+    ///
+    /// The database is only queried for columns that no overlay provides.
+    /// Returns `None` if no layer has a row for the address.
+    ///
+    /// # Errors
+    ///
+    /// See [`Database::query()`].
+    pub fn query(&self, addr: IpAddr, query: Columns) -> Result<Option<LayeredRow>, Error> {
+        let mut missing = query & Columns::all();
+        let mut found = false;
+        let mut lookup = LayeredRow::default();
+        for (i, overlay) in self.overlays.iter().enumerate().rev() {
+            if let Some(row) = overlay.get(addr) {
+                found = true;
+                lookup.fill(Layer::Overlay(i), row, &mut missing);
+            }
+        }
+        if !missing.is_empty() || !found {
+            if let Some(row) = self.db.query(addr, missing)? {
+                found = true;
+                lookup.fill(Layer::Database, &row, &mut missing);
+            }
+        }
+        Ok(found.then_some(lookup))
+    }
+}
+
+impl<R> From<Database<R>> for LayeredDatabase<R> {
+    fn from(db: Database<R>) -> LayeredDatabase<R> {
+        LayeredDatabase::new(db)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(column: Columns, value: &str) -> Row {
+        let mut row = Row::default();
+        row.set(column, Some(value.to_owned()));
+        row
+    }
+
+    #[test]
+    fn test_layers() {
+        let db = Database::open("data/IP2PROXY-IP-PROXYTYPE-COUNTRY-REGION-CITY-ISP.SAMPLE.BIN")
+            .unwrap();
+
+        let mut lower = Overlay::new("lower");
+        lower
+            .insert(
+                "1.0.0.0".parse().unwrap(),
+                "1.0.0.255".parse().unwrap(),
+                row(Columns::PROXY_TYPE, "VPN"),
+            )
+            .unwrap();
+        let mut upper = Overlay::new("upper");
+        upper
+            .insert(
+                "::ffff:1.0.0.0".parse().unwrap(),
+                "::ffff:1.0.0.15".parse().unwrap(),
+                row(Columns::PROXY_TYPE, "TOR"),
+            )
+            .unwrap();
+        assert!(
+            upper
+                .insert(
+                    "1.0.0.15".parse().unwrap(),
+                    "1.0.0.16".parse().unwrap(),
+                    Row::default(),
+                )
+                .is_err()
+        );
+
+        let mut db = LayeredDatabase::new(db);
+        db.push_overlay(lower).push_overlay(upper);
+
+        let lookup = db
+            .query("1.0.0.1".parse().unwrap(), Columns::all())
+            .unwrap()
+            .unwrap();
+        assert_eq!(lookup.row.proxy_type.as_deref(), Some("TOR"));
+        assert_eq!(lookup.source(Columns::PROXY_TYPE), Some(Layer::Overlay(1)));
+        assert_eq!(lookup.row.city.as_deref(), Some("Brisbane"));
+        assert_eq!(lookup.source(Columns::CITY), Some(Layer::Database));
+        assert_eq!(lookup.source(Columns::THREAT), None);
+
+        let lookup = db
+            .query("1.0.0.16".parse().unwrap(), Columns::PROXY_TYPE)
+            .unwrap()
+            .unwrap();
+        assert_eq!(lookup.row.proxy_type.as_deref(), Some("VPN"));
+        assert_eq!(lookup.source(Columns::PROXY_TYPE), Some(Layer::Overlay(0)));
+        assert_eq!(lookup.row.city, None);
+    }
+
+    #[test]
+    fn test_overlay_6to4_teredo() {
+        let mut overlay = Overlay::new("tunnels");
+        overlay
+            .insert(
+                "2002::".parse().unwrap(),
+                "2002:ffff:ffff:ffff:ffff:ffff:ffff:ffff".parse().unwrap(),
+                row(Columns::PROXY_TYPE, "6to4"),
+            )
+            .unwrap();
+        overlay
+            .insert(
+                "1.0.0.0".parse().unwrap(),
+                "1.0.0.255".parse().unwrap(),
+                row(Columns::PROXY_TYPE, "VPN"),
+            )
+            .unwrap();
+
+        // The IPv6 range takes precedence over the embedded IPv4 address.
+        let row = overlay.get("2002:100:1::".parse().unwrap()).unwrap();
+        assert_eq!(row.proxy_type.as_deref(), Some("6to4"));
+
+        // Teredo (client 1.0.0.1, obfuscated) falls back to the IPv4 range.
+        let row = overlay
+            .get("2001:0:4136:e378:8000:63bf:feff:fffe".parse().unwrap())
+            .unwrap();
+        assert_eq!(row.proxy_type.as_deref(), Some("VPN"));
+        let row = overlay.get("::ffff:1.0.0.1".parse().unwrap()).unwrap();
+        assert_eq!(row.proxy_type.as_deref(), Some("VPN"));
+        assert_eq!(overlay.get("2001:0:4136:e378::".parse().unwrap()), None);
+    }
+}
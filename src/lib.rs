@@ -4,7 +4,8 @@
 //! [https://www.ip2location.com/](https://www.ip2location.com/) is a
 //! commercial provider, offering various database files for download.
 //!
-//! Supports IPv4 and IPv6.
+//! Supports IPv4 and IPv6. IP2Location geolocation databases (DB1 - DB26)
+//! can be read with the [`ip2location`] module.
 //!
 //! # Example
 //!
@@ -30,22 +31,87 @@
 //! # Cargo features
 //!
 //! * `serde`: Implement `serde::Serialize` and `serde::Deserialize` for `Row`.
-
-#![forbid(unsafe_code)]
+//! * `mmap`: Adds [`Database::open_mmap()`] to memory-map database files.
+//! * `async`: Adds `AsyncDatabase` for lookups from async code, using the
+//!   blocking thread pool of the [tokio](https://tokio.rs) runtime.
+//! * `csv`: Adds `DatabaseBuilder::from_csv()` and `Database::export_csv()`
+//!   to convert from and to IP2Proxy CSV distributions, and
+//!   `Overlay::from_csv()`.
+//! * `json`: Adds `Database::export_json_lines()` and
+//!   `Overlay::from_json_lines()`.
+//! * `rayon`: Adds `Database::par_query()` to look up many addresses in
+//!   parallel, using [rayon](https://docs.rs/rayon).
+//! * `cache`: Adds `CachedDatabase`, with a cache of recently matched
+//!   rows.
+//! * `cli`: Build the `ip2proxy` command-line tool, to look up addresses and
+//!   inspect database files.
+
+#![cfg_attr(not(feature = "mmap"), forbid(unsafe_code))]
+#![cfg_attr(feature = "mmap", deny(unsafe_code))]
 #![warn(missing_docs)]
 #![warn(missing_debug_implementations)]
 
+#[cfg(feature = "async")]
+mod async_database;
+mod builder;
+#[cfg(feature = "cache")]
+mod cache;
+mod cidr;
+mod date;
+mod diff;
+mod error;
+#[cfg(any(feature = "csv", feature = "json"))]
+mod export;
+mod filter;
+#[cfg(feature = "csv")]
+mod import;
+mod intern;
+pub mod ip2location;
+mod iter;
+mod layered;
+mod mmdb;
+#[cfg(feature = "rayon")]
+mod par;
+mod reload;
+mod row_ref;
+mod source;
+mod types;
+mod validate;
+
 use std::{
-    cmp::min,
-    io,
+    cell::RefCell,
+    cmp::{max, min},
+    fmt, io,
     io::{ErrorKind, Read},
     net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    ops::RangeInclusive,
     path::Path,
 };
 
 use bitflags::bitflags;
 use byteorder::{ByteOrder as _, LE, ReadBytesExt as _};
-use positioned_io::{Cursor, RandomAccessFile, ReadAt, ReadBytesAtExt as _};
+use positioned_io::{Cursor, RandomAccessFile, ReadAt};
+
+#[cfg(feature = "async")]
+pub use async_database::AsyncDatabase;
+pub use builder::DatabaseBuilder;
+#[cfg(feature = "cache")]
+pub use cache::{CacheStats, CachedDatabase};
+pub use cidr::Cidr;
+pub use diff::{Change, ChangeCounts, ChangeKind, Diff, DiffSummary, diff};
+pub use error::{Error, InvalidInput};
+pub use filter::{Filter, Find};
+pub use intern::{InternedDatabase, SharedRow};
+pub use iter::{Family, Iter};
+pub use layered::{Layer, LayeredDatabase, LayeredRow, Overlay};
+pub use mmdb::MmdbLayout;
+/// Re-exported for implementing custom storage backends, see [`Source`].
+pub use positioned_io;
+pub use reload::ReloadableDatabase;
+pub use row_ref::RowRef;
+pub use source::Source;
+pub use types::{CountryCode, InvalidField, ProxyType, Threat, UsageType};
+pub use validate::{Problem, ValidationReport};
 
 bitflags! {
     /// Set of supported or selected columns.
@@ -256,30 +322,217 @@ pub struct Row {
     pub provider: Option<String>,
 }
 
-impl Row {
-    /// Checks if the row represents a known proxy of any kind.
-    pub fn is_proxy(&self) -> Option<bool> {
-        if let Some(ref country_short) = self.country_short {
-            if country_short == "-" {
-                return Some(false);
+/// Implements `field()` and `field_mut()` to access the field of a single
+/// column, for row types with one `Option` field per column.
+macro_rules! impl_column_fields {
+    (@fields $row:ty, $value:ty, $($column:ident => $field:ident,)*) => {
+        impl $row {
+            /// Get the field of `column`, or `None` if `column` is not
+            /// exactly one column.
+            fn field(&self, column: Columns) -> Option<&Option<$value>> {
+                Some(match column {
+                    $(Columns::$column => &self.$field,)*
+                    _ => return None,
+                })
+            }
+
+            /// Get the mutable field of `column`, or `None` if `column` is
+            /// not exactly one column.
+            fn field_mut(&mut self, column: Columns) -> Option<&mut Option<$value>> {
+                Some(match column {
+                    $(Columns::$column => &mut self.$field,)*
+                    _ => return None,
+                })
             }
         }
-        if let Some(ref proxy_type) = self.proxy_type {
-            return Some(proxy_type != "-");
+    };
+    ($row:ty, $value:ty) => {
+        impl_column_fields! {
+            @fields $row, $value,
+            PROXY_TYPE => proxy_type,
+            COUNTRY_SHORT => country_short,
+            COUNTRY_LONG => country_long,
+            REGION => region,
+            CITY => city,
+            ISP => isp,
+            DOMAIN => domain,
+            USAGE_TYPE => usage_type,
+            ASN => asn,
+            AS_NAME => as_name,
+            LAST_SEEN => last_seen,
+            THREAT => threat,
+            PROVIDER => provider,
+        }
+    };
+}
+
+pub(crate) use impl_column_fields;
+
+impl_column_fields!(Row, String);
+
+impl Row {
+    /// Get the value of a single column.
+    ///
+    /// Returns `None` if the column is not set, or if `column` is not
+    /// exactly one column.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ip2proxy::{Columns, Row};
+    ///
+    /// let mut row = Row::default();
+    /// row.country_short = Some(String::from("AU"));
+    /// assert_eq!(row.get(Columns::COUNTRY_SHORT), Some("AU"));
+    /// assert_eq!(row.get(Columns::COUNTRY_LONG), None);
+    /// ```
+    pub fn get(&self, column: Columns) -> Option<&str> {
+        self.field(column)?.as_deref()
+    }
+
+    /// Set the value of a single column.
+    ///
+    /// Does nothing if `column` is not exactly one column.
+    pub fn set(&mut self, column: Columns, value: Option<String>) {
+        if let Some(field) = self.field_mut(column) {
+            *field = value;
         }
-        None
+    }
+
+    /// Checks if the row represents a known proxy of any kind.
+    pub fn is_proxy(&self) -> Option<bool> {
+        types::is_proxy(self.country_short.as_deref(), self.proxy_type.as_deref())
+    }
+
+    /// Get the type of proxy, if any. See [`Row::proxy_type`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ip2proxy::{ProxyType, Row};
+    ///
+    /// let mut row = Row::default();
+    /// row.proxy_type = Some(String::from("VPN"));
+    /// assert_eq!(row.parsed_proxy_type(), Some(ProxyType::Vpn));
+    ///
+    /// row.proxy_type = Some(String::from("-"));
+    /// assert_eq!(row.parsed_proxy_type(), None);
+    /// ```
+    pub fn parsed_proxy_type(&self) -> Option<ProxyType> {
+        types::parse_proxy_type(self.proxy_type.as_deref())
+    }
+
+    /// Get the usage type classifications. Some ranges have multiple
+    /// classifications, like `ISP/MOB`. See [`Row::usage_type`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ip2proxy::{Row, UsageType};
+    ///
+    /// let mut row = Row::default();
+    /// row.usage_type = Some(String::from("DCH/SES"));
+    /// assert_eq!(
+    ///     row.parsed_usage_types(),
+    ///     [UsageType::DataCenter, UsageType::SearchEngineSpider]
+    /// );
+    /// ```
+    pub fn parsed_usage_types(&self) -> Vec<UsageType> {
+        types::parse_codes(self.usage_type.as_deref())
+    }
+
+    /// Get the reported security threats. Some ranges have multiple
+    /// threats, like `SPAM/SCANNER`. See [`Row::threat`].
+    pub fn parsed_threats(&self) -> Vec<Threat> {
+        types::parse_codes(self.threat.as_deref())
+    }
+
+    /// Get the country code. See [`Row::country_short`].
+    ///
+    /// # Errors
+    ///
+    /// The field is not empty or `-`, and not a valid country code.
+    pub fn parsed_country(&self) -> Result<Option<CountryCode>, InvalidField> {
+        types::parse_country(self.country_short.as_deref())
+    }
+
+    /// Get the Autonomous System Number (ASN). See [`Row::asn`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ip2proxy::Row;
+    ///
+    /// let mut row = Row::default();
+    /// row.asn = Some(String::from("13335"));
+    /// assert_eq!(row.parsed_asn(), Ok(Some(13335)));
+    ///
+    /// row.asn = Some(String::from("-"));
+    /// assert_eq!(row.parsed_asn(), Ok(None));
+    ///
+    /// row.asn = Some(String::from("AS13335"));
+    /// assert!(row.parsed_asn().is_err());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// The field is not empty or `-`, and not a valid number.
+    pub fn parsed_asn(&self) -> Result<Option<u32>, InvalidField> {
+        types::parse_number(Columns::ASN, self.asn.as_deref())
+    }
+
+    /// Get the number of days since the proxy was last seen. See
+    /// [`Row::last_seen`].
+    ///
+    /// # Errors
+    ///
+    /// The field is not empty or `-`, and not a valid number.
+    pub fn parsed_last_seen(&self) -> Result<Option<u32>, InvalidField> {
+        types::parse_number(Columns::LAST_SEEN, self.last_seen.as_deref())
+    }
+}
+
+/// Result of [`Database::query_range()`]: A row and the range of addresses
+/// it applies to.
+#[non_exhaustive]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Lookup {
+    /// The matched row.
+    pub row: Row,
+    /// The matched range of addresses, including both bounds.
+    pub range: RangeInclusive<IpAddr>,
+}
+
+impl Lookup {
+    /// Get the smallest list of CIDR networks that exactly cover the
+    /// matched range.
+    pub fn cidrs(&self) -> Vec<Cidr> {
+        Cidr::covering(self.range.clone())
     }
 }
 
 /// An IP2Proxy BIN database.
-#[derive(Debug)]
-pub struct Database {
-    raf: RandomAccessFile,
+///
+/// The database is generic over its storage backend, a [`Source`]. By
+/// default it reads from a [`RandomAccessFile`], but it can also be backed
+/// by bytes in memory (see [`Database::from_bytes()`] and
+/// [`Database::from_static()`]), or by a memory-mapped file with the `mmap`
+/// feature.
+pub struct Database<R = RandomAccessFile> {
+    reader: R,
     header: Header,
     index_ipv4: Option<IndexTable>,
     index_ipv6: Option<IndexTable>,
 }
 
+impl<R> fmt::Debug for Database<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Database")
+            .field("header", &self.header)
+            .finish_non_exhaustive()
+    }
+}
+
 impl Database {
     /// Open a database file.
     ///
@@ -297,19 +550,148 @@ impl Database {
     /// * Error while opening the file.
     /// * Error while reading from the file.
     /// * Invalid data in header section or index section.
-    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
-        Self::new(RandomAccessFile::open(path)?)
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::from_file(RandomAccessFile::open(path)?)
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl Database<Mmap> {
+    /// Open a database file by mapping it into memory. Lookups are then
+    /// plain memory reads, without any system calls.
+    ///
+    /// The file must not be modified or truncated while it is mapped.
+    /// Replace it atomically (for example by renaming a new file over it)
+    /// instead.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ip2proxy::Database;
+    ///
+    /// let db = Database::open_mmap("data/IP2PROXY-IP-PROXYTYPE-COUNTRY-REGION-CITY-ISP.SAMPLE.BIN")?;
+    /// # Ok::<_, Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * Error while opening or mapping the file.
+    /// * Invalid data in header section or index section.
+    pub fn open_mmap<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let file = std::fs::File::open(path)?;
+        // Safety: The documentation of this function requires that the file
+        // is not modified while it is mapped.
+        #[allow(unsafe_code)]
+        let mmap = unsafe { memmap2::Mmap::map(&file)? };
+        Self::from_file(Mmap(mmap))
+    }
+}
+
+impl Database<Vec<u8>> {
+    /// Load a database from bytes in memory.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ip2proxy::Database;
+    ///
+    /// let bytes = std::fs::read("data/IP2PROXY-IP-PROXYTYPE-COUNTRY-REGION-CITY-ISP.SAMPLE.BIN")?;
+    /// let db = Database::from_bytes(bytes)?;
+    /// assert_eq!(db.package_version(), 4);
+    /// # Ok::<_, Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * Invalid data in header section or index section.
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, Error> {
+        Self::from_file(bytes)
+    }
+}
+
+impl Database<&'static [u8]> {
+    /// Load a database from static bytes, for example a file embedded with
+    /// [`include_bytes!`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ip2proxy::Database;
+    ///
+    /// static BIN: &[u8] = include_bytes!("../data/IP2PROXY-IP-PROXYTYPE-COUNTRY-REGION-CITY-ISP.SAMPLE.BIN");
+    ///
+    /// let db = Database::from_static(BIN)?;
+    /// assert_eq!(db.package_version(), 4);
+    /// # Ok::<_, Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * Invalid data in header section or index section.
+    pub fn from_static(bytes: &'static [u8]) -> Result<Self, Error> {
+        Self::from_file(bytes)
+    }
+}
+
+impl<R: Source> Database<R> {
+    /// Open a database from any storage backend, for example a slice of a
+    /// larger container file. Backends in memory are read directly, like
+    /// [`Database::from_bytes()`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::fs::File;
+    ///
+    /// use ip2proxy::Database;
+    ///
+    /// let file = File::open("data/IP2PROXY-IP-PROXYTYPE-COUNTRY-REGION-CITY-ISP.SAMPLE.BIN")?;
+    /// let db = Database::from_reader(file)?;
+    /// assert_eq!(db.package_version(), 4);
+    /// # Ok::<_, Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * Error while reading from the source.
+    /// * Unsupported package version ([`Error::UnsupportedPackage`]) or
+    ///   invalid number of columns ([`Error::InvalidColumnCount`]) in header
+    ///   section.
+    /// * Not an IP2Proxy database, according to the product code in the
+    ///   extended header of newer releases ([`Error::UnexpectedProduct`]).
+    /// * The source is shorter than the file size in the extended header
+    ///   ([`Error::FileSizeMismatch`]). Trailing data is allowed, so the
+    ///   source may also be part of a larger file.
+    /// * Index section too short.
+    pub fn from_reader(reader: R) -> Result<Self, Error> {
+        Self::from_reader_with(reader, false, Header::check_px)
+    }
+
+    /// Open a source that is known to contain exactly the database file.
+    fn from_file(reader: R) -> Result<Self, Error> {
+        Self::from_reader_with(reader, true, Header::check_px)
     }
 
-    fn new(raf: RandomAccessFile) -> io::Result<Self> {
-        let mut header_buf = [0; HEADER_LEN];
-        raf.read_exact_at(0, &mut header_buf)?;
+    /// Open a BIN file, with a check of the header before reading the
+    /// indexes. If `whole_file` is set, the source must end exactly at the
+    /// file size declared in the extended header.
+    fn from_reader_with(
+        reader: R,
+        whole_file: bool,
+        check: impl FnOnce(&Header) -> Result<(), Error>,
+    ) -> Result<Self, Error> {
+        let mut header_buf = [0; EXTENDED_HEADER_LEN];
+        reader.read_exact_at(0, &mut header_buf)?;
         let header = Header::read(&header_buf[..])?;
+        check(&header)?;
+        if let Some(extended) = header.extended {
+            extended.check_file_size(&reader, whole_file)?;
+        }
 
         Ok(Database {
             index_ipv4: if header.index_ptr_ipv4 != 0 {
                 Some(IndexTable::read(Cursor::new_pos(
-                    &raf,
+                    &reader,
                     u64::from(header.index_ptr_ipv4) - 1,
                 ))?)
             } else {
@@ -317,14 +699,14 @@ impl Database {
             },
             index_ipv6: if header.index_ptr_ipv6 != 0 {
                 Some(IndexTable::read(Cursor::new_pos(
-                    &raf,
+                    &reader,
                     u64::from(header.index_ptr_ipv6) - 1,
                 ))?)
             } else {
                 None
             },
             header,
-            raf,
+            reader,
         })
     }
 
@@ -350,61 +732,178 @@ impl Database {
     /// # Errors
     ///
     /// * Error while reading from the source.
+    /// * Invalid row or string data ([`Error::CorruptIndex`],
+    ///   [`Error::SearchOutOfBounds`], [`Error::InvalidString`]).
+    pub fn query(&self, addr: IpAddr, query: Columns) -> Result<Option<Row>, Error> {
+        match self.find_row(addr)? {
+            Some(raw) => Ok(Some(self.read_row(raw.cols(), query)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Look up information for an IP address into an existing row, reusing
+    /// the allocations of its strings. Returns `false` if there is no
+    /// information for the address, in which case the row is unchanged.
+    ///
+    /// Useful in loops that process one row at a time.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ip2proxy::{Columns, Database, Row};
+    ///
+    /// let db = Database::open("data/IP2PROXY-IP-PROXYTYPE-COUNTRY-REGION-CITY-ISP.SAMPLE.BIN")?;
+    ///
+    /// let mut row = Row::default();
+    /// for addr in ["1.0.0.1".parse()?, "1.0.4.1".parse()?] {
+    ///     assert!(db.query_into(addr, Columns::all(), &mut row)?);
+    ///     assert_eq!(Some(&row), db.query(addr, Columns::all())?.as_ref());
+    /// }
+    /// # Ok::<_, Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * Error while reading from the source.
+    /// * Invalid row or string data. Fields of the row may have been
+    ///   overwritten.
+    pub fn query_into(&self, addr: IpAddr, query: Columns, row: &mut Row) -> Result<bool, Error> {
+        match self.find_row(addr)? {
+            Some(raw) => {
+                self.read_row_into(raw.cols(), query, row)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Look up information for an IP address, together with the range of
+    /// addresses that share the same row.
+    ///
+    /// IPv4-mapped, 6to4 and Teredo addresses are looked up as IPv4
+    /// addresses, so the returned range is an IPv4 range in that case.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ip2proxy::{Columns, Database};
+    ///
+    /// let db = Database::open("data/IP2PROXY-IP-PROXYTYPE-COUNTRY-REGION-CITY-ISP.SAMPLE.BIN")?;
+    ///
+    /// let lookup = db.query_range("1.0.0.1".parse()?, Columns::PROXY_TYPE)?.unwrap();
+    /// assert_eq!(lookup.row.proxy_type, Some(String::from("DCH")));
+    /// assert_eq!(lookup.range, "1.0.0.0".parse()?..="1.0.0.255".parse()?);
+    /// assert_eq!(lookup.cidrs()[0].to_string(), "1.0.0.0/24");
+    /// # Ok::<_, Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * Error while reading from the source.
+    /// * Invalid row or string data.
+    pub fn query_range(&self, addr: IpAddr, query: Columns) -> Result<Option<Lookup>, Error> {
+        match self.find_row(addr)? {
+            Some(raw) => Ok(Some(Lookup {
+                row: self.read_row(raw.cols(), query)?,
+                range: raw.range(),
+            })),
+            None => Ok(None),
+        }
+    }
+
+    /// Look up information for many IP addresses at once.
+    ///
+    /// Equivalent to calling [`Database::query()`] for each address, but
+    /// addresses are processed in ascending order, so that neighbouring
+    /// addresses can narrow the binary search, and addresses in the same
+    /// range share a single read of the row.
+    ///
+    /// Returns the results in the order of the given addresses.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ip2proxy::{Columns, Database};
+    ///
+    /// let db = Database::open("data/IP2PROXY-IP-PROXYTYPE-COUNTRY-REGION-CITY-ISP.SAMPLE.BIN")?;
+    ///
+    /// let addrs = ["1.0.0.2".parse()?, "1.0.0.1".parse()?];
+    /// let rows = db.query_many(&addrs, Columns::PROXY_TYPE)?;
+    /// assert_eq!(rows[0], db.query(addrs[0], Columns::PROXY_TYPE)?);
+    /// assert_eq!(rows[1], db.query(addrs[1], Columns::PROXY_TYPE)?);
+    /// # Ok::<_, Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * Error while reading from the source.
     /// * Invalid row or string data.
-    pub fn query(&self, addr: IpAddr, query: Columns) -> io::Result<Option<Row>> {
-        let addr = normalize_ip(addr);
+    pub fn query_many(&self, addrs: &[IpAddr], query: Columns) -> Result<Vec<Option<Row>>, Error> {
+        let mut order: Vec<(IpAddr, usize)> = addrs
+            .iter()
+            .enumerate()
+            .map(|(i, addr)| (clamp_ip(normalize_ip(*addr)), i))
+            .collect();
+        order.sort_unstable();
+
+        let mut rows = vec![None; addrs.len()];
+        let mut prev: Option<(u32, RawRow, Row)> = None;
+        for (addr, i) in order {
+            if let Some((_, raw, row)) = &prev {
+                if raw.from <= addr && addr < raw.to {
+                    rows[i] = Some(row.clone());
+                    continue;
+                }
+            }
 
+            // Rows of later addresses cannot be before the previous row of
+            // the same table.
+            let after = prev
+                .as_ref()
+                .filter(|(_, raw, _)| raw.from.is_ipv6() == addr.is_ipv6())
+                .map_or(0, |(index, _, _)| *index);
+            if let Some((index, raw)) = self.search_row(addr, after)? {
+                let row = self.read_row(raw.cols(), query)?;
+                rows[i] = Some(row.clone());
+                prev = Some((index, raw, row));
+            }
+        }
+        Ok(rows)
+    }
+
+    fn find_row(&self, addr: IpAddr) -> Result<Option<RawRow>, Error> {
+        Ok(self
+            .search_row(clamp_ip(normalize_ip(addr)), 0)?
+            .map(|(_, raw)| raw))
+    }
+
+    /// Binary search for the row containing a normalized and clamped
+    /// address, among the rows given by the index, but not before the
+    /// row with index `after`. Returns the index of the row and the row.
+    fn search_row(&self, addr: IpAddr, after: u32) -> Result<Option<(u32, RawRow)>, Error> {
         if let Some(RowRange {
             mut low_row,
             mut high_row,
         }) = self.query_index(addr)
         {
-            let (base_ptr, addr_size) = if addr.is_ipv4() {
-                (self.header.base_ptr_ipv4, 4)
-            } else {
-                (self.header.base_ptr_ipv6, 16)
-            };
-
-            if base_ptr == 0 {
+            let Some(table) = self.table(addr.is_ipv6()) else {
                 return Ok(None);
-            }
-
-            let row_size = addr_size + (usize::from(self.header.num_columns) - 1) * 4;
-
-            let addr = match addr {
-                IpAddr::V4(addr) => IpAddr::V4(min(addr, Ipv4Addr::from(u32::MAX - 1))),
-                IpAddr::V6(addr) => IpAddr::V6(min(addr, Ipv6Addr::from(u128::MAX - 1))),
             };
-
-            let mut buffer = [0; 16 + 16 + (MAX_COLUMNS - 1) * 4];
+            if high_row >= table.rows {
+                return Err(Error::CorruptIndex);
+            }
+            low_row = max(low_row, after);
 
             while low_row <= high_row {
                 let mid_row = mid(low_row, high_row);
+                let raw = self.read_raw_row(&table, mid_row)?;
 
-                let row_ptr = u64::from(base_ptr) + u64::from(mid_row) * row_size as u64 - 1; // base_ptr > 0, row_size small
-                let buf = &mut buffer[..(row_size + addr_size)];
-                self.raf.read_exact_at(row_ptr, buf)?; // row
-
-                let below = match addr {
-                    IpAddr::V4(addr) => addr < Ipv4Addr::from(LE::read_u32(buf)),
-                    IpAddr::V6(addr) => addr < Ipv6Addr::from(LE::read_u128(buf)),
-                };
-
-                let above = match addr {
-                    IpAddr::V4(addr) => addr >= Ipv4Addr::from(LE::read_u32(&buf[row_size..])),
-                    IpAddr::V6(addr) => addr >= Ipv6Addr::from(LE::read_u128(&buf[row_size..])),
-                };
-
-                if below {
-                    high_row = mid_row.checked_sub(1).ok_or_else(|| {
-                        io::Error::new(ErrorKind::InvalidData, "underflow in binary search")
-                    })?;
-                } else if above {
-                    low_row = mid_row.checked_add(1).ok_or_else(|| {
-                        io::Error::new(ErrorKind::InvalidData, "overflow in binary search")
-                    })?;
+                if addr < raw.from {
+                    high_row = mid_row.checked_sub(1).ok_or(Error::SearchOutOfBounds)?;
+                } else if addr >= raw.to {
+                    low_row = mid_row.checked_add(1).ok_or(Error::SearchOutOfBounds)?;
                 } else {
-                    return Ok(Some(self.read_row(&buf[addr_size..row_size], query)?));
+                    return Ok(Some((mid_row, raw)));
                 }
             }
         }
@@ -412,80 +911,104 @@ impl Database {
         Ok(None)
     }
 
-    fn read_row(&self, buf: &[u8], query: Columns) -> io::Result<Row> {
-        let mut cursor = io::Cursor::new(buf);
-
-        let proxy_type = self.read_col(&mut cursor, query, Columns::PROXY_TYPE)?;
-        let (country_short, country_long) = self.read_country_col(&mut cursor, query)?;
-
-        Ok(Row {
-            proxy_type,
-            country_short,
-            country_long,
-            region: self.read_col(&mut cursor, query, Columns::REGION)?,
-            city: self.read_col(&mut cursor, query, Columns::CITY)?,
-            isp: self.read_col(&mut cursor, query, Columns::ISP)?,
-            domain: self.read_col(&mut cursor, query, Columns::DOMAIN)?,
-            usage_type: self.read_col(&mut cursor, query, Columns::USAGE_TYPE)?,
-            asn: self.read_col(&mut cursor, query, Columns::ASN)?,
-            as_name: self.read_col(&mut cursor, query, Columns::AS_NAME)?,
-            last_seen: self.read_col(&mut cursor, query, Columns::LAST_SEEN)?,
-            threat: self.read_col(&mut cursor, query, Columns::THREAT)?,
-            provider: self.read_col(&mut cursor, query, Columns::PROVIDER)?,
+    fn table(&self, ipv6: bool) -> Option<Table> {
+        let (base_ptr, rows, addr_size) = if ipv6 {
+            (self.header.base_ptr_ipv6, self.header.rows_ipv6, 16)
+        } else {
+            (self.header.base_ptr_ipv4, self.header.rows_ipv4, 4)
+        };
+
+        if base_ptr == 0 {
+            return None;
+        }
+
+        Some(Table {
+            base_ptr,
+            rows,
+            addr_size,
+            row_size: addr_size + (usize::from(self.header.num_columns) - 1) * 4,
         })
     }
 
-    fn read_country_col<R: Read>(
-        &self,
-        mut reader: R,
-        query: Columns,
-    ) -> io::Result<(Option<String>, Option<String>)> {
-        if self
-            .header
-            .columns
-            .intersects(Columns::COUNTRY_SHORT | Columns::COUNTRY_LONG)
-        {
-            let ptr = u64::from(reader.read_u32::<LE>()?);
-            let country_short = if query.contains(Columns::COUNTRY_SHORT) {
-                Some(self.read_str(ptr)?)
-            } else {
-                None
-            };
-            let country_long = if query.contains(Columns::COUNTRY_LONG) {
-                Some(self.read_str(ptr + 3)?) // ptr <= u32::MAX
-            } else {
-                None
-            };
-            Ok((country_short, country_long))
-        } else {
-            Ok((None, None))
+    fn read_raw_row(&self, table: &Table, index: u32) -> Result<RawRow, Error> {
+        let row_ptr = u64::from(table.base_ptr) + u64::from(index) * table.row_size as u64 - 1; // base_ptr > 0, row_size small
+        let len = table.row_size + table.addr_size; // row and start of next row
+        match self.reader.as_bytes() {
+            Some(bytes) => Ok(RawRow::decode(
+                table,
+                usize::try_from(row_ptr)
+                    .ok()
+                    .and_then(|row_ptr| bytes.get(row_ptr..)?.get(..len))
+                    .ok_or(io::Error::from(ErrorKind::UnexpectedEof))?,
+            )),
+            None => with_scratch(len, |buf| {
+                self.reader.read_exact_at(row_ptr, buf)?;
+                Ok(RawRow::decode(table, buf))
+            }),
         }
     }
 
-    fn read_col<R: Read>(
-        &self,
-        mut reader: R,
-        query: Columns,
-        column: Columns,
-    ) -> io::Result<Option<String>> {
-        if self.header.columns.contains(column) {
-            let ptr = u64::from(reader.read_u32::<LE>()?);
-            if query.contains(column) {
-                return Ok(Some(self.read_str(ptr)?));
+    fn read_row(&self, cols: &[u8], query: Columns) -> Result<Row, Error> {
+        let mut row = Row::default();
+        self.read_row_into(cols, query, &mut row)?;
+        Ok(row)
+    }
+
+    /// Read the selected columns into `row`, reusing the allocations of
+    /// its strings. Other fields are set to `None`.
+    fn read_row_into(&self, cols: &[u8], query: Columns, row: &mut Row) -> Result<(), Error> {
+        for column in Columns::all().iter() {
+            let field = row.field_mut(column).expect("single column");
+            match self
+                .col_ptr(cols, column)?
+                .filter(|_| query.contains(column))
+            {
+                Some(ptr) => self.read_str_with(ptr, |s| {
+                    let field = field.get_or_insert_default();
+                    field.clear();
+                    field.push_str(s);
+                })?,
+                None => *field = None,
             }
         }
-        Ok(None)
+        Ok(())
+    }
+
+    fn col_ptr(&self, cols: &[u8], column: Columns) -> Result<Option<u64>, Error> {
+        col_ptr(self.header.columns, cols, column)
+    }
+
+    fn read_str(&self, ptr: u64) -> Result<String, Error> {
+        self.read_str_with(ptr, ToOwned::to_owned)
     }
 
-    fn read_str(&self, ptr: u64) -> io::Result<String> {
+    /// Read a string and convert it, without an intermediate allocation.
+    fn read_str_with<T>(&self, ptr: u64, f: impl FnOnce(&str) -> T) -> Result<T, Error> {
+        if let Some(bytes) = self.reader.as_bytes() {
+            return str_at(bytes, ptr).map(f);
+        }
+
         // +-----+-------+-------+-----+
         // | len | buf 0 | buf 1 | ... |
         // +-----+-------+-------+-----+
-        let len = self.raf.read_u8_at(ptr)?;
-        let mut buf = vec![0; usize::from(len)];
-        self.raf.read_exact_at(ptr + 1, &mut buf)?; // ptr <= u32::MAX + 3
-        String::from_utf8(buf)
-            .map_err(|_| io::Error::new(ErrorKind::InvalidData, "invalid utf-8 data"))
+        //
+        // Strings are short, so read the maximum length at once, rather
+        // than reading the length first.
+        with_scratch(1 + usize::from(u8::MAX), |buf| {
+            let mut filled = 0;
+            while filled == 0 || filled < 1 + usize::from(buf[0]) {
+                match self.reader.read_at(ptr + filled as u64, &mut buf[filled..]) {
+                    Ok(0) => return Err(io::Error::from(ErrorKind::UnexpectedEof).into()),
+                    Ok(n) => filled += n,
+                    Err(err) if err.kind() == ErrorKind::Interrupted => (),
+                    Err(err) => return Err(err.into()),
+                }
+            }
+            let s = &buf[1..1 + usize::from(buf[0])];
+            std::str::from_utf8(s)
+                .map(f)
+                .map_err(|_| Error::InvalidString { ptr })
+        })
     }
 
     fn query_index(&self, addr: IpAddr) -> Option<RowRange> {
@@ -501,7 +1024,9 @@ impl Database {
                 .map(|i| i.table[usize::from(addr.segments()[0])]),
         }
     }
+}
 
+impl<R> Database<R> {
     /// Get package version.
     ///
     /// # Example
@@ -563,6 +1088,37 @@ impl Database {
         self.header.day
     }
 
+    /// Get the product code from the extended header of newer releases,
+    /// `2` for IP2Proxy. `None` for older releases without extended header.
+    pub fn product_code(&self) -> Option<u8> {
+        self.header.extended.map(|extended| extended.product_code)
+    }
+
+    /// Get the license code from the extended header of newer releases.
+    /// `None` for older releases without extended header.
+    pub fn license_code(&self) -> Option<u8> {
+        self.header.extended.map(|extended| extended.license_code)
+    }
+
+    /// Get the file size in bytes from the extended header of newer
+    /// releases. Opening a database file verifies that it has exactly this
+    /// size, and [`Database::from_reader()`] verifies that the source has
+    /// at least this size. `None` for older releases without extended
+    /// header.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ip2proxy::Database;
+    ///
+    /// let db = Database::open("data/IP2PROXY-IP-PROXYTYPE-COUNTRY-REGION-CITY-ISP.SAMPLE.BIN")?;
+    /// assert_eq!(db.file_size(), None);
+    /// # Ok::<_, Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn file_size(&self) -> Option<u32> {
+        self.header.extended.map(|extended| extended.file_size)
+    }
+
     /// Get the number of rows for IPv4 addresses. Rows can cover a range,
     /// so there may be information for many more IP addresses.
     pub fn rows_ipv4(&self) -> u32 {
@@ -576,8 +1132,47 @@ impl Database {
     }
 }
 
+/// A memory-mapped database file.
+///
+/// See [`Database::open_mmap()`].
+#[cfg(feature = "mmap")]
+#[derive(Debug)]
+pub struct Mmap(memmap2::Mmap);
+
+#[cfg(feature = "mmap")]
+impl ReadAt for Mmap {
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> io::Result<usize> {
+        (&self.0[..]).read_at(pos, buf)
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl positioned_io::Size for Mmap {
+    fn size(&self) -> io::Result<Option<u64>> {
+        Ok(Some(self.0.len() as u64))
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl AsRef<[u8]> for Mmap {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl Source for Mmap {
+    fn as_bytes(&self) -> Option<&[u8]> {
+        Some(&self.0)
+    }
+}
+
 const FROM_6TO4: u128 = 0x2002_0000_0000_0000_0000_0000_0000_0000;
 const TO_6TO4: u128 = 0x2002_ffff_ffff_ffff_ffff_ffff_ffff_ffff;
+#[cfg(any(feature = "csv", feature = "json"))]
+const MAPPED_FROM: u128 = 0x0000_0000_0000_0000_0000_ffff_0000_0000;
+#[cfg(any(feature = "csv", feature = "json"))]
+const MAPPED_TO: u128 = 0x0000_0000_0000_0000_0000_ffff_ffff_ffff;
 const FROM_TEREDO: u128 = 0x2001_0000_0000_0000_0000_0000_0000_0000;
 const TO_TEREDO: u128 = 0x2001_0000_ffff_ffff_ffff_ffff_ffff_ffff;
 
@@ -598,6 +1193,76 @@ fn normalize_ip(addr: IpAddr) -> IpAddr {
     }
 }
 
+thread_local! {
+    /// Buffer for positioned reads, shared by all lookups on a thread.
+    static SCRATCH: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Call `f` with a buffer of length `len`, borrowed from the scratch
+/// buffer of the current thread. The buffer may contain data of previous
+/// reads. Falls back to a new buffer if the scratch buffer is already in
+/// use, for example by a source that looks up another database while
+/// reading.
+fn with_scratch<T>(len: usize, f: impl FnOnce(&mut [u8]) -> T) -> T {
+    SCRATCH.with(|scratch| match scratch.try_borrow_mut() {
+        Ok(mut buf) => {
+            if buf.len() < len {
+                buf.resize(len, 0);
+            }
+            f(&mut buf[..len])
+        }
+        Err(_) => f(&mut vec![0; len]),
+    })
+}
+
+/// Get the string at `ptr` from the bytes of a database in memory.
+fn str_at(bytes: &[u8], ptr: u64) -> Result<&str, Error> {
+    // +-----+-------+-------+-----+
+    // | len | buf 0 | buf 1 | ... |
+    // +-----+-------+-------+-----+
+    let s = usize::try_from(ptr)
+        .ok()
+        .and_then(|ptr| {
+            let len = usize::from(*bytes.get(ptr)?);
+            bytes.get(ptr + 1..ptr + 1 + len)
+        })
+        .ok_or(io::Error::from(ErrorKind::UnexpectedEof))?;
+    std::str::from_utf8(s).map_err(|_| Error::InvalidString { ptr })
+}
+
+/// Get the string pointer of a column from the columns of a row, given the
+/// set of columns of the database.
+fn col_ptr(columns: Columns, cols: &[u8], column: Columns) -> Result<Option<u64>, Error> {
+    let mut offset = 0;
+    for col in COLUMN_LAYOUT {
+        if columns.intersects(col) {
+            if col.intersects(column) {
+                let ptr = u64::from(
+                    cols.get(offset..offset + 4)
+                        .ok_or(io::Error::from(ErrorKind::UnexpectedEof))?
+                        .read_u32::<LE>()?,
+                );
+                return Ok(Some(if column == Columns::COUNTRY_LONG {
+                    ptr + 3 // ptr <= u32::MAX
+                } else {
+                    ptr
+                }));
+            }
+            offset += 4;
+        }
+    }
+    Ok(None)
+}
+
+/// The highest address of each family is reserved, and shares the row of
+/// the address before it.
+fn clamp_ip(addr: IpAddr) -> IpAddr {
+    match addr {
+        IpAddr::V4(addr) => IpAddr::V4(min(addr, Ipv4Addr::from(u32::MAX - 1))),
+        IpAddr::V6(addr) => IpAddr::V6(min(addr, Ipv6Addr::from(u128::MAX - 1))),
+    }
+}
+
 fn mid(low_row: u32, high_row: u32) -> u32 {
     ((u64::from(low_row) + u64::from(high_row)) / 2) as u32
 }
@@ -615,27 +1280,20 @@ struct Header {
     base_ptr_ipv6: u32,
     index_ptr_ipv4: u32,
     index_ptr_ipv6: u32,
+    extended: Option<ExtendedHeader>,
     columns: Columns,
 }
 
 impl Header {
-    fn read<R: Read>(mut reader: R) -> io::Result<Header> {
+    fn read<R: Read>(mut reader: R) -> Result<Header, Error> {
         let px = reader.read_u8()?;
-        let columns = PX
-            .get(usize::from(px))
-            .copied()
-            .unwrap_or_else(Columns::empty);
-        if columns.is_empty() {
-            return Err(io::Error::new(
-                ErrorKind::InvalidData,
-                "only px1 - px11 supported",
-            ));
-        }
-
         Ok(Header {
             px,
-            columns,
-            num_columns: validate_columns(reader.read_u8()?)?,
+            columns: PX
+                .get(usize::from(px))
+                .copied()
+                .unwrap_or_else(Columns::empty),
+            num_columns: reader.read_u8()?,
             year: reader.read_u8()?,
             month: reader.read_u8()?,
             day: reader.read_u8()?,
@@ -645,14 +1303,96 @@ impl Header {
             base_ptr_ipv6: reader.read_u32::<LE>()?,
             index_ptr_ipv4: reader.read_u32::<LE>()?,
             index_ptr_ipv6: reader.read_u32::<LE>()?,
+            extended: ExtendedHeader::read(reader)?,
         })
     }
 }
 
+/// Header fields of newer releases, following the fields covered by
+/// [`HEADER_LEN`]. Older releases have zeros in their place.
+#[derive(Debug, Copy, Clone)]
+struct ExtendedHeader {
+    product_code: u8,
+    license_code: u8,
+    file_size: u32,
+}
+
+impl ExtendedHeader {
+    fn read<R: Read>(mut reader: R) -> Result<Option<ExtendedHeader>, Error> {
+        let product_code = reader.read_u8()?;
+        let license_code = reader.read_u8()?;
+        let file_size = reader.read_u32::<LE>()?;
+        Ok((product_code != 0).then_some(ExtendedHeader {
+            product_code,
+            license_code,
+            file_size,
+        }))
+    }
+
+    fn check_product(&self, product_code: u8) -> Result<(), Error> {
+        if self.product_code != product_code {
+            return Err(Error::UnexpectedProduct {
+                product_code: self.product_code,
+            });
+        }
+        Ok(())
+    }
+
+    fn check_file_size<R: ReadAt>(&self, reader: &R, exact: bool) -> Result<(), Error> {
+        // The last byte must exist. If exact, nothing may follow.
+        let mut buf = [0; 1];
+        let complete = match u64::from(self.file_size).checked_sub(1) {
+            Some(last) => {
+                reader.read_at(last, &mut buf)? == 1
+                    && (!exact || reader.read_at(u64::from(self.file_size), &mut buf)? == 0)
+            }
+            None => false,
+        };
+        if !complete {
+            return Err(Error::FileSizeMismatch {
+                expected: self.file_size,
+            });
+        }
+        Ok(())
+    }
+}
+
 const HEADER_LEN: usize = 5 + 6 * 4;
 
+const EXTENDED_HEADER_LEN: usize = HEADER_LEN + 2 + 4;
+
+/// Product code of IP2Location files in the extended header.
+const PRODUCT_IP2LOCATION: u8 = 1;
+
+/// Product code of IP2Proxy files in the extended header.
+const PRODUCT_IP2PROXY: u8 = 2;
+
 const MAX_COLUMNS: usize = 13;
 
+/// Maximum number of columns of any supported BIN file, including
+/// IP2Location files.
+const MAX_BIN_COLUMNS: usize = 25;
+
+/// Maximum length of the columns of a row, without the start address.
+const MAX_COLS_LEN: usize = (MAX_BIN_COLUMNS - 1) * 4;
+
+/// Order of columns in a row. Country short and long names share a
+/// single column.
+const COLUMN_LAYOUT: [Columns; MAX_COLUMNS - 1] = [
+    Columns::PROXY_TYPE,
+    Columns::COUNTRY_SHORT.union(Columns::COUNTRY_LONG),
+    Columns::REGION,
+    Columns::CITY,
+    Columns::ISP,
+    Columns::DOMAIN,
+    Columns::USAGE_TYPE,
+    Columns::ASN,
+    Columns::AS_NAME,
+    Columns::LAST_SEEN,
+    Columns::THREAT,
+    Columns::PROVIDER,
+];
+
 const PX: [Columns; 12] = [
     Columns::empty(),
     Columns::PX1,
@@ -668,14 +1408,80 @@ const PX: [Columns; 12] = [
     Columns::PX11,
 ];
 
-fn validate_columns(num_columns: u8) -> io::Result<u8> {
-    if num_columns < 1 || MAX_COLUMNS < usize::from(num_columns) {
-        Err(io::Error::new(
-            ErrorKind::InvalidData,
-            "invalid number of columns",
-        ))
-    } else {
-        Ok(num_columns)
+impl Header {
+    fn check_px(&self) -> Result<(), Error> {
+        if let Some(extended) = self.extended {
+            extended.check_product(PRODUCT_IP2PROXY)?;
+        }
+        if self.columns.is_empty() {
+            return Err(Error::UnsupportedPackage { px: self.px });
+        }
+        if self.num_columns < 1 || MAX_COLUMNS < usize::from(self.num_columns) {
+            return Err(Error::InvalidColumnCount {
+                num_columns: self.num_columns,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Table {
+    base_ptr: u32,
+    rows: u32,
+    addr_size: usize,
+    row_size: usize,
+}
+
+#[derive(Debug)]
+struct RawRow {
+    from: IpAddr,
+    to: IpAddr,
+    cols: [u8; MAX_COLS_LEN],
+    cols_len: usize,
+}
+
+impl RawRow {
+    /// Decode a row, followed by the start address of the next row.
+    fn decode(table: &Table, buf: &[u8]) -> RawRow {
+        let (from, to) = if table.addr_size == 4 {
+            (
+                IpAddr::V4(Ipv4Addr::from(LE::read_u32(buf))),
+                IpAddr::V4(Ipv4Addr::from(LE::read_u32(&buf[table.row_size..]))),
+            )
+        } else {
+            (
+                IpAddr::V6(Ipv6Addr::from(LE::read_u128(buf))),
+                IpAddr::V6(Ipv6Addr::from(LE::read_u128(&buf[table.row_size..]))),
+            )
+        };
+
+        let cols_len = table.row_size - table.addr_size;
+        let mut cols = [0; MAX_COLS_LEN];
+        cols[..cols_len].copy_from_slice(&buf[table.addr_size..table.row_size]);
+        RawRow {
+            from,
+            to,
+            cols,
+            cols_len,
+        }
+    }
+
+    fn cols(&self) -> &[u8] {
+        &self.cols[..self.cols_len]
+    }
+
+    fn range(&self) -> RangeInclusive<IpAddr> {
+        // Start of the next row is exclusive, and always greater than the
+        // start of this row. The highest address of each family is reserved
+        // for the sentinel row, but is looked up in the last row.
+        let last = match self.to {
+            IpAddr::V4(to) if to == Ipv4Addr::from(u32::MAX) => self.to,
+            IpAddr::V6(to) if to == Ipv6Addr::from(u128::MAX) => self.to,
+            IpAddr::V4(to) => IpAddr::V4(Ipv4Addr::from(u32::from(to) - 1)),
+            IpAddr::V6(to) => IpAddr::V6(Ipv6Addr::from(u128::from(to) - 1)),
+        };
+        self.from..=last
     }
 }
 
@@ -707,6 +1513,28 @@ impl IndexTable {
 mod tests {
     use super::*;
 
+    pub(crate) const SAMPLE: &str = "data/IP2PROXY-IP-PROXYTYPE-COUNTRY-REGION-CITY-ISP.SAMPLE.BIN";
+
+    /// Addresses of the sample database in different ranges, with their
+    /// country codes.
+    pub(crate) const SAMPLE_COUNTRIES: [(&str, &str); 5] = [
+        ("1.0.0.1", "AU"),
+        ("1.0.1.1", "-"),
+        ("1.0.16.1", "JP"),
+        ("1.0.233.24", "TH"),
+        ("223.241.95.171", "CN"),
+    ];
+
+    /// The first and last address of each range of a database.
+    pub(crate) fn bounds<R: Source>(db: &Database<R>) -> Vec<IpAddr> {
+        let mut addrs = Vec::new();
+        for res in db.iter(Columns::empty()) {
+            let (start, end, _) = res.unwrap();
+            addrs.extend([start, end]);
+        }
+        addrs
+    }
+
     #[test]
     fn test_teredo() {
         let ipv6 = "2001:0:4136:e378:8000:63bf:3fff:fdd2".parse().unwrap();
@@ -720,4 +1548,206 @@ mod tests {
         let ipv4: IpAddr = "10.11.22.33".parse().unwrap();
         assert_eq!(normalize_ip(ipv6), ipv4);
     }
+
+    #[test]
+    fn test_query_many() {
+        let db = Database::open(SAMPLE).unwrap();
+
+        let mut addrs = bounds(&db);
+        addrs.reverse();
+        addrs.extend([
+            "1.0.0.1".parse::<IpAddr>().unwrap(),
+            "::ffff:1.0.0.1".parse().unwrap(),
+            "255.255.255.255".parse().unwrap(),
+            "ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff".parse().unwrap(),
+            "2001:db8::1".parse().unwrap(),
+        ]);
+
+        let rows = db.query_many(&addrs, Columns::all()).unwrap();
+        assert_eq!(rows.len(), addrs.len());
+        for (addr, row) in addrs.iter().zip(rows) {
+            assert_eq!(row, db.query(*addr, Columns::all()).unwrap(), "{addr}");
+        }
+    }
+
+    #[test]
+    fn test_in_memory() {
+        static BIN: &[u8] =
+            include_bytes!("../data/IP2PROXY-IP-PROXYTYPE-COUNTRY-REGION-CITY-ISP.SAMPLE.BIN");
+        let file = Database::open(SAMPLE).unwrap();
+        let bytes = Database::from_bytes(BIN.to_vec()).unwrap();
+        let from_static = Database::from_static(BIN).unwrap();
+        let from_reader = Database::from_reader(BIN.to_vec()).unwrap();
+        assert!(file.reader.as_bytes().is_none());
+        assert!(bytes.reader.as_bytes().is_some());
+        assert!(from_static.reader.as_bytes().is_some());
+        assert!(from_reader.reader.as_bytes().is_some());
+
+        // Slices are read like files.
+        for addr in bounds(&file) {
+            let row = file.query(addr, Columns::all()).unwrap();
+            assert_eq!(bytes.query(addr, Columns::all()).unwrap(), row, "{addr}");
+        }
+
+        // Strings out of bounds.
+        assert!(bytes.read_str(BIN.len() as u64).is_err());
+        assert!(bytes.read_str(u64::MAX).is_err());
+    }
+
+    #[test]
+    fn test_query_into() {
+        let db = Database::open("data/IP2PROXY-IP-PROXYTYPE-COUNTRY-REGION-CITY-ISP.SAMPLE.BIN")
+            .unwrap();
+        let query = Columns::COUNTRY_LONG | Columns::CITY;
+
+        let mut row = Row {
+            country_long: Some(String::with_capacity(256)),
+            city: Some(String::with_capacity(256)),
+            isp: Some(String::from("stale")),
+            ..Row::default()
+        };
+        let ptrs = (
+            row.country_long.as_ref().unwrap().as_ptr(),
+            row.city.as_ref().unwrap().as_ptr(),
+        );
+        for addr in ["1.0.0.1", "1.0.4.1", "2001:200::1"] {
+            let addr = addr.parse().unwrap();
+            assert!(db.query_into(addr, query, &mut row).unwrap());
+            assert_eq!(Some(&row), db.query(addr, query).unwrap().as_ref());
+            assert_eq!(
+                (
+                    row.country_long.as_ref().unwrap().as_ptr(),
+                    row.city.as_ref().unwrap().as_ptr()
+                ),
+                ptrs,
+                "{addr}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_with_scratch() {
+        // Nested use falls back to a new buffer.
+        let (outer, inner) = with_scratch(4, |outer| {
+            outer.copy_from_slice(b"abcd");
+            let inner = with_scratch(8, |inner| {
+                inner.fill(0);
+                inner.len()
+            });
+            (outer.to_vec(), inner)
+        });
+        assert_eq!((outer.as_slice(), inner), (&b"abcd"[..], 8));
+    }
+
+    #[test]
+    fn test_row_columns() {
+        // Each column has its own field.
+        let mut row = Row::default();
+        for (name, column) in Columns::all().iter_names() {
+            row.set(column, Some(name.to_owned()));
+        }
+        for (name, column) in Columns::all().iter_names() {
+            assert_eq!(row.get(column), Some(name));
+        }
+        assert_eq!(row.get(Columns::empty()), None);
+        assert_eq!(row.get(Columns::CITY | Columns::ISP), None);
+    }
+
+    #[test]
+    fn test_query_range_max() {
+        let db = Database::open("data/IP2PROXY-IP-PROXYTYPE-COUNTRY-REGION-CITY-ISP.SAMPLE.BIN")
+            .unwrap();
+
+        for addr in [
+            "255.255.255.255".parse::<IpAddr>().unwrap(),
+            "ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff".parse().unwrap(),
+        ] {
+            let lookup = db.query_range(addr, Columns::all()).unwrap().unwrap();
+            assert!(lookup.range.contains(&addr), "{addr}");
+            assert_eq!(Some(lookup.row), db.query(addr, Columns::all()).unwrap());
+        }
+    }
+
+    /// A database with a single row for 10.0.0.0 - 10.0.0.255, with the
+    /// index bucket of 10.0 replaced by the given first and last row.
+    pub(crate) fn with_index_bucket(low_row: u32, high_row: u32) -> Vec<u8> {
+        let mut builder = DatabaseBuilder::new(1).unwrap();
+        builder.date(24, 1, 1);
+        builder
+            .push(
+                "10.0.0.0".parse().unwrap(),
+                "10.0.0.255".parse().unwrap(),
+                Row::default(),
+            )
+            .unwrap();
+        let mut bytes = Vec::new();
+        builder.write(&mut bytes).unwrap();
+        let bucket = 64 + (10 << 8) * 8; // IPv4 index, upper 16 bits 10.0
+        bytes[bucket..bucket + 4].copy_from_slice(&low_row.to_le_bytes());
+        bytes[bucket + 4..bucket + 8].copy_from_slice(&high_row.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn test_index_bucket() {
+        // Empty bucket.
+        let db = Database::from_bytes(with_index_bucket(1, 0)).unwrap();
+        assert_eq!(
+            db.query("10.0.0.1".parse().unwrap(), Columns::all())
+                .unwrap(),
+            None
+        );
+
+        // Out of bounds.
+        let db = Database::from_bytes(with_index_bucket(0, u32::MAX)).unwrap();
+        assert!(matches!(
+            db.query("10.0.0.1".parse().unwrap(), Columns::all()),
+            Err(Error::CorruptIndex)
+        ));
+    }
+
+    #[test]
+    fn test_extended_header() {
+        let mut builder = DatabaseBuilder::new(1).unwrap();
+        builder.date(24, 5, 1);
+        let mut bytes = Vec::new();
+        builder.write(&mut bytes).unwrap();
+
+        let db = Database::from_bytes(bytes.clone()).unwrap();
+        assert_eq!(db.product_code(), Some(PRODUCT_IP2PROXY));
+        assert_eq!(db.license_code(), Some(0));
+        assert_eq!(db.file_size(), Some(bytes.len() as u32));
+
+        let mut truncated = bytes.clone();
+        truncated.pop();
+        assert!(matches!(
+            Database::from_bytes(truncated.clone()),
+            Err(Error::FileSizeMismatch { .. })
+        ));
+        assert!(matches!(
+            Database::from_reader(truncated),
+            Err(Error::FileSizeMismatch { .. })
+        ));
+
+        let mut extended = bytes.clone();
+        extended.push(0);
+        assert!(matches!(
+            Database::from_bytes(extended),
+            Err(Error::FileSizeMismatch { .. })
+        ));
+
+        // Part of a larger source.
+        let mut container = bytes.clone();
+        container.extend_from_slice(b"trailing data");
+        let db = Database::from_reader(container).unwrap();
+        assert_eq!(db.file_size(), Some(bytes.len() as u32));
+
+        let mut ip2location = bytes;
+        ip2location[HEADER_LEN] = PRODUCT_IP2LOCATION;
+        assert!(matches!(
+            Database::from_bytes(ip2location.clone()),
+            Err(Error::UnexpectedProduct { product_code: 1 })
+        ));
+        assert!(crate::ip2location::Database::from_bytes(ip2location).is_ok());
+    }
 }
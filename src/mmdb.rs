@@ -0,0 +1,550 @@
+use std::{
+    collections::HashMap,
+    io::{self, ErrorKind, Write},
+    net::IpAddr,
+};
+
+use crate::{Columns, Database, Row, Source, cidr::cover, date};
+
+const METADATA_START_MARKER: &[u8] = b"\xab\xcd\xefMaxMind.com";
+const DATA_SECTION_SEPARATOR_LEN: u32 = 16;
+
+/// Mapping from [`Row`] fields to keys of MaxMind DB records, used with
+/// [`Database::export_mmdb()`].
+///
+/// Keys may be nested with dots, so that `country.iso_code` results in
+/// a record like `{"country": {"iso_code": "DE"}}`. All values are written
+/// as strings.
+///
+/// # Example
+///
+/// ```
+/// use ip2proxy::{Columns, MmdbLayout};
+///
+/// let layout = MmdbLayout::new()
+///     .field(Columns::PROXY_TYPE, "proxy.type")
+///     .field(Columns::COUNTRY_SHORT, "country.iso_code")
+///     .field(Columns::COUNTRY_LONG, "country.names.en")
+///     .database_type("IP2Proxy-Country");
+///
+/// assert_eq!(layout.columns(), Columns::PROXY_TYPE | Columns::COUNTRY_SHORT | Columns::COUNTRY_LONG);
+/// ```
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct MmdbLayout {
+    fields: Vec<(Columns, String)>,
+    database_type: Option<String>,
+}
+
+impl MmdbLayout {
+    /// Create a layout without any fields.
+    pub fn new() -> MmdbLayout {
+        MmdbLayout::default()
+    }
+
+    /// Create a layout with every column at the top level of the record,
+    /// keyed by its lowercase name, like `proxy_type`.
+    pub fn flat() -> MmdbLayout {
+        Columns::all()
+            .iter_names()
+            .fold(MmdbLayout::new(), |layout, (name, column)| {
+                layout.field(column, name.to_ascii_lowercase())
+            })
+    }
+
+    /// Write the value of `column` at `key`.
+    pub fn field(mut self, column: Columns, key: impl Into<String>) -> MmdbLayout {
+        self.fields.push((column, key.into()));
+        self
+    }
+
+    /// Set the `database_type` of the metadata. Defaults to
+    /// `IP2Proxy-PX<n>`.
+    pub fn database_type(mut self, database_type: impl Into<String>) -> MmdbLayout {
+        self.database_type = Some(database_type.into());
+        self
+    }
+
+    /// Get the set of columns used by the layout.
+    pub fn columns(&self) -> Columns {
+        self.fields
+            .iter()
+            .fold(Columns::empty(), |acc, (column, _)| acc | *column)
+    }
+
+    fn record(&self, row: &Row) -> io::Result<Option<Map>> {
+        let mut record = Map::default();
+        for (column, key) in &self.fields {
+            let Some(value) = row.get(*column).filter(|v| !v.is_empty() && *v != "-") else {
+                continue;
+            };
+            if !record.insert(&key.split('.').collect::<Vec<_>>(), value) {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("conflicting mmdb key: {key:?}"),
+                ));
+            }
+        }
+        Ok((!record.0.is_empty()).then_some(record))
+    }
+}
+
+impl<R: Source> Database<R> {
+    /// Export the database as a [MaxMind DB](https://maxmind.github.io/MaxMind-DB/)
+    /// file, with records in the given layout.
+    ///
+    /// The file has IP version 6 if the database has an IPv6 table.
+    /// In this case IPv4 ranges are stored at `::/96`, with aliases at
+    /// `::ffff:0:0/96` (IPv4-mapped) and `2002::/16` (6to4), replacing
+    /// any ranges of the IPv6 table there. Teredo addresses cannot be
+    /// aliased and are looked up in the IPv6 table.
+    ///
+    /// Fields that are empty or `-` are omitted. Ranges without any
+    /// remaining fields are not stored, so that lookups find no record,
+    /// just like for addresses that are not in the database.
+    ///
+    /// The search tree is built in memory.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ip2proxy::{Database, MmdbLayout};
+    ///
+    /// let db = Database::open("data/IP2PROXY-IP-PROXYTYPE-COUNTRY-REGION-CITY-ISP.SAMPLE.BIN")?;
+    ///
+    /// let mut mmdb = Vec::new();
+    /// db.export_mmdb(&mut mmdb, &MmdbLayout::flat())?;
+    /// # Ok::<_, Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * Error while reading from the source or writing.
+    /// * Invalid row or string data.
+    /// * Conflicting keys in the layout ([`ErrorKind::InvalidInput`]).
+    /// * The file would exceed the maximum size of the format.
+    pub fn export_mmdb<W: Write>(&self, mut writer: W, layout: &MmdbLayout) -> io::Result<()> {
+        let query = layout.columns() & self.columns();
+        let ipv6 = self.table(true).is_some();
+        let bits = if ipv6 { 128 } else { 32 };
+
+        let mut tree = Tree::new();
+        let mut data = DataSection::default();
+
+        if ipv6 {
+            for res in self.iter_ipv6(query) {
+                let (start, end, row) = res?;
+                if let Some(record) = layout.record(&row)? {
+                    let ptr = data.insert(&record);
+                    cover(value(start), value(end), 128, |prefix, prefix_len| {
+                        tree.set(prefix, 128, prefix_len, Record::Data(ptr))
+                    });
+                }
+            }
+            tree.set(0, 128, 96, Record::Empty);
+        }
+
+        let offset = bits - 32;
+        for res in self.iter_ipv4(query) {
+            let (start, end, row) = res?;
+            if let Some(record) = layout.record(&row)? {
+                let ptr = data.insert(&record);
+                cover(value(start), value(end), 32, |prefix, prefix_len| {
+                    tree.set(prefix, bits, prefix_len + offset as u8, Record::Data(ptr))
+                });
+            }
+        }
+
+        if ipv6 {
+            let ipv4 = tree.get(0, 128, 96);
+            tree.set(0xffff_0000_0000, 128, 96, ipv4);
+            tree.set(0x2002 << 112, 128, 16, ipv4);
+        }
+
+        let nodes = tree.finish();
+        let node_count = to_u32(nodes.len())?;
+        let data_len = to_u32(data.buf.len())?;
+        let record_size = [24, 28, 32]
+            .into_iter()
+            .find(|size| {
+                u64::from(node_count) + u64::from(DATA_SECTION_SEPARATOR_LEN) + u64::from(data_len)
+                    <= 1 << size
+            })
+            .ok_or_else(|| io::Error::other("too large for mmdb"))?;
+
+        let resolve = |record: Record| match record {
+            Record::Empty => node_count,
+            Record::Node(node) => node,
+            Record::Data(ptr) => node_count + DATA_SECTION_SEPARATOR_LEN + ptr,
+        };
+        for [left, right] in nodes {
+            let (left, right) = (resolve(left), resolve(right));
+            match record_size {
+                24 => {
+                    writer.write_all(&left.to_be_bytes()[1..])?;
+                    writer.write_all(&right.to_be_bytes()[1..])?;
+                }
+                28 => {
+                    writer.write_all(&left.to_be_bytes()[1..])?;
+                    writer.write_all(&[((left >> 20) & 0xf0) as u8 | (right >> 24) as u8])?;
+                    writer.write_all(&right.to_be_bytes()[1..])?;
+                }
+                _ => {
+                    writer.write_all(&left.to_be_bytes())?;
+                    writer.write_all(&right.to_be_bytes())?;
+                }
+            }
+        }
+
+        writer.write_all(&[0; DATA_SECTION_SEPARATOR_LEN as usize])?;
+        writer.write_all(&data.buf)?;
+
+        let database_type = layout
+            .database_type
+            .clone()
+            .unwrap_or_else(|| format!("IP2Proxy-PX{}", self.package_version()));
+        let mut metadata = Vec::new();
+        encode_control(&mut metadata, MAP, 9);
+        encode_string(&mut metadata, "binary_format_major_version");
+        encode_uint(&mut metadata, UINT16, 2);
+        encode_string(&mut metadata, "binary_format_minor_version");
+        encode_uint(&mut metadata, UINT16, 0);
+        encode_string(&mut metadata, "build_epoch");
+        encode_uint(&mut metadata, UINT64, self.build_epoch());
+        encode_string(&mut metadata, "database_type");
+        encode_string(&mut metadata, &database_type);
+        encode_string(&mut metadata, "description");
+        encode_control(&mut metadata, MAP, 0);
+        encode_string(&mut metadata, "ip_version");
+        encode_uint(&mut metadata, UINT16, if ipv6 { 6 } else { 4 });
+        encode_string(&mut metadata, "languages");
+        encode_control(&mut metadata, ARRAY, 0);
+        encode_string(&mut metadata, "node_count");
+        encode_uint(&mut metadata, UINT32, u64::from(node_count));
+        encode_string(&mut metadata, "record_size");
+        encode_uint(&mut metadata, UINT16, record_size);
+
+        writer.write_all(METADATA_START_MARKER)?;
+        writer.write_all(&metadata)?;
+        writer.flush()
+    }
+
+    /// Seconds since the Unix epoch at the start of the database date.
+    fn build_epoch(&self) -> u64 {
+        let days = date::days_from_civil(
+            2000 + i64::from(self.year()),
+            self.month().clamp(1, 12),
+            self.day().max(1),
+        );
+        days as u64 * 86400 // year >= 2000
+    }
+}
+
+fn value(addr: IpAddr) -> u128 {
+    match addr {
+        IpAddr::V4(addr) => u128::from(u32::from(addr)),
+        IpAddr::V6(addr) => u128::from(addr),
+    }
+}
+
+fn to_u32(n: usize) -> io::Result<u32> {
+    u32::try_from(n).map_err(|_| io::Error::other("too large for mmdb"))
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Record {
+    Empty,
+    Node(u32),
+    Data(u32),
+}
+
+/// Binary search tree over address bits, built in memory.
+struct Tree {
+    nodes: Vec<[Record; 2]>,
+}
+
+impl Tree {
+    fn new() -> Tree {
+        Tree {
+            nodes: vec![[Record::Empty; 2]],
+        }
+    }
+
+    fn bit(prefix: u128, bits: u32, depth: u32) -> usize {
+        ((prefix >> (bits - 1 - depth)) & 1) as usize
+    }
+
+    /// Get the record at the end of the path `prefix/prefix_len`.
+    fn get(&self, prefix: u128, bits: u32, prefix_len: u8) -> Record {
+        let mut node = 0;
+        for depth in 0..u32::from(prefix_len) {
+            match self.nodes[node][Tree::bit(prefix, bits, depth)] {
+                Record::Node(next) => node = next as usize,
+                record => return record,
+            }
+        }
+        Record::Node(node as u32)
+    }
+
+    /// Replace the record at the end of the path `prefix/prefix_len`,
+    /// splitting records along the way.
+    fn set(&mut self, prefix: u128, bits: u32, prefix_len: u8, record: Record) {
+        if prefix_len == 0 {
+            self.nodes = vec![[record; 2]];
+            return;
+        }
+        let mut node = 0;
+        for depth in 0..u32::from(prefix_len) - 1 {
+            let bit = Tree::bit(prefix, bits, depth);
+            node = match self.nodes[node][bit] {
+                Record::Node(next) => next as usize,
+                split => {
+                    self.nodes.push([split; 2]);
+                    let next = self.nodes.len() - 1;
+                    self.nodes[node][bit] = Record::Node(next as u32);
+                    next
+                }
+            };
+        }
+        let bit = Tree::bit(prefix, bits, u32::from(prefix_len) - 1);
+        self.nodes[node][bit] = record;
+    }
+
+    /// Get the reachable nodes, with the root first, merging nodes whose
+    /// records are equal.
+    fn finish(self) -> Vec<[Record; 2]> {
+        let mut memo = vec![None; self.nodes.len()];
+        let mut out = Vec::new();
+        let root = self.nodes[0];
+        let left = self.resolve(root[0], &mut memo, &mut out);
+        let right = self.resolve(root[1], &mut memo, &mut out);
+        out.push([left, right]);
+
+        // Nodes were numbered in post-order. Reverse so that the root is
+        // the first node.
+        let last = out.len() as u32 - 1;
+        out.reverse();
+        for node in &mut out {
+            for record in node {
+                if let Record::Node(n) = record {
+                    *n = last - *n;
+                }
+            }
+        }
+        out
+    }
+
+    fn resolve(
+        &self,
+        record: Record,
+        memo: &mut [Option<Record>],
+        out: &mut Vec<[Record; 2]>,
+    ) -> Record {
+        let Record::Node(node) = record else {
+            return record;
+        };
+        if let Some(resolved) = memo[node as usize] {
+            return resolved;
+        }
+        let [left, right] = self.nodes[node as usize];
+        let left = self.resolve(left, memo, out);
+        let right = self.resolve(right, memo, out);
+        let resolved = if left == right && !matches!(left, Record::Node(_)) {
+            left
+        } else {
+            out.push([left, right]);
+            Record::Node(out.len() as u32 - 1)
+        };
+        memo[node as usize] = Some(resolved);
+        resolved
+    }
+}
+
+#[derive(Debug, Default)]
+struct Map(Vec<(String, Value)>);
+
+impl Map {
+    fn insert(&mut self, path: &[&str], value: &str) -> bool {
+        let Some((key, path)) = path.split_first() else {
+            return false;
+        };
+        let nested = !path.is_empty();
+        let entry = match self.0.iter().position(|(k, _)| k == key) {
+            Some(pos) => &mut self.0[pos].1,
+            None => {
+                self.0.push((
+                    (*key).to_owned(),
+                    if nested {
+                        Value::Map(Map::default())
+                    } else {
+                        Value::String(value.to_owned())
+                    },
+                ));
+                &mut self.0.last_mut().expect("pushed").1
+            }
+        };
+        match entry {
+            Value::Map(map) if nested => map.insert(path, value),
+            Value::String(existing) => !nested && existing == value,
+            Value::Map(_) => false,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Value {
+    String(String),
+    Map(Map),
+}
+
+/// Data section with deduplicated records.
+#[derive(Default)]
+struct DataSection {
+    buf: Vec<u8>,
+    offsets: HashMap<Vec<u8>, u32>,
+}
+
+impl DataSection {
+    fn insert(&mut self, record: &Map) -> u32 {
+        let mut encoded = Vec::new();
+        encode_map(&mut encoded, record);
+        let next = self.buf.len() as u32;
+        *self.offsets.entry(encoded).or_insert_with_key(|encoded| {
+            self.buf.extend_from_slice(encoded);
+            next
+        })
+    }
+}
+
+const STRING: u8 = 2;
+const UINT16: u8 = 5;
+const UINT32: u8 = 6;
+const MAP: u8 = 7;
+const UINT64: u8 = 9;
+const ARRAY: u8 = 11;
+
+fn encode_control(buf: &mut Vec<u8>, data_type: u8, size: usize) {
+    let (size_bits, extra): (u8, &[u8]) = match size {
+        0..29 => (size as u8, &[]),
+        29..285 => (29, &(size - 29).to_be_bytes()[7..]),
+        285..65821 => (30, &(size - 285).to_be_bytes()[6..]),
+        _ => (31, &(size - 65821).to_be_bytes()[5..]),
+    };
+    if data_type <= 7 {
+        buf.push(data_type << 5 | size_bits);
+    } else {
+        buf.push(size_bits);
+        buf.push(data_type - 7);
+    }
+    buf.extend_from_slice(extra);
+}
+
+fn encode_string(buf: &mut Vec<u8>, s: &str) {
+    encode_control(buf, STRING, s.len());
+    buf.extend_from_slice(s.as_bytes());
+}
+
+fn encode_uint(buf: &mut Vec<u8>, data_type: u8, n: u64) {
+    let bytes = n.to_be_bytes();
+    let skip = n.leading_zeros() as usize / 8;
+    encode_control(buf, data_type, 8 - skip);
+    buf.extend_from_slice(&bytes[skip..]);
+}
+
+fn encode_map(buf: &mut Vec<u8>, map: &Map) {
+    encode_control(buf, MAP, map.0.len());
+    for (key, value) in &map.0 {
+        encode_string(buf, key);
+        match value {
+            Value::String(s) => encode_string(buf, s),
+            Value::Map(map) => encode_map(buf, map),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct Country<'a> {
+        iso_code: &'a str,
+    }
+
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct Nested<'a> {
+        #[serde(borrow)]
+        country: Country<'a>,
+        proxy_type: &'a str,
+    }
+
+    #[test]
+    fn test_mmdb_matches_query() {
+        let db = Database::open("data/IP2PROXY-IP-PROXYTYPE-COUNTRY-REGION-CITY-ISP.SAMPLE.BIN")
+            .unwrap();
+
+        let mut buf = Vec::new();
+        db.export_mmdb(&mut buf, &MmdbLayout::flat()).unwrap();
+        let reader = maxminddb::Reader::from_source(buf).unwrap();
+        assert_eq!(reader.metadata.ip_version, 6);
+        assert_eq!(reader.metadata.database_type, "IP2Proxy-PX4");
+        assert_eq!(reader.metadata.build_epoch, 1479340800); // 2016-11-17
+
+        for addr in [
+            "1.0.0.1",
+            "1.0.4.1",
+            "::ffff:1.0.0.1",
+            "2002:100:1::",
+            "2001:db8::1",
+            "::1.0.0.1",
+            "255.255.255.254",
+        ] {
+            let addr: IpAddr = addr.parse().unwrap();
+            let expected = db.query(addr, Columns::all()).unwrap().and_then(|row| {
+                let record: BTreeMap<_, _> = Columns::all()
+                    .iter_names()
+                    .filter_map(|(name, column)| {
+                        row.get(column)
+                            .filter(|v| *v != "-")
+                            .map(|v| (name.to_ascii_lowercase(), v.to_owned()))
+                    })
+                    .collect();
+                (!record.is_empty()).then_some(record)
+            });
+            let actual = match reader.lookup::<BTreeMap<String, String>>(addr) {
+                Ok(record) => Some(record),
+                Err(maxminddb::MaxMindDBError::AddressNotFoundError(_)) => None,
+                Err(err) => panic!("{addr}: {err}"),
+            };
+            assert_eq!(actual, expected, "{addr}");
+        }
+    }
+
+    #[test]
+    fn test_mmdb_nested() {
+        let db = Database::open("data/IP2PROXY-IP-PROXYTYPE-COUNTRY-REGION-CITY-ISP.SAMPLE.BIN")
+            .unwrap();
+        let layout = MmdbLayout::new()
+            .field(Columns::COUNTRY_SHORT, "country.iso_code")
+            .field(Columns::PROXY_TYPE, "proxy_type");
+
+        let mut buf = Vec::new();
+        db.export_mmdb(&mut buf, &layout).unwrap();
+        let reader = maxminddb::Reader::from_source(buf).unwrap();
+        let record: Nested = reader.lookup("1.0.0.1".parse().unwrap()).unwrap();
+        assert_eq!(
+            record,
+            Nested {
+                country: Country { iso_code: "AU" },
+                proxy_type: "DCH",
+            }
+        );
+
+        let conflict = layout.field(Columns::ISP, "country");
+        assert_eq!(
+            db.export_mmdb(io::sink(), &conflict).unwrap_err().kind(),
+            ErrorKind::InvalidInput
+        );
+    }
+}
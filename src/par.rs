@@ -0,0 +1,87 @@
+use std::net::IpAddr;
+
+use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator as _};
+
+use crate::{Columns, Database, Error, Row, Source};
+
+impl<R: Source + Sync> Database<R> {
+    /// Look up information for many IP addresses in parallel, on the
+    /// [rayon](https://docs.rs/rayon) thread pool.
+    ///
+    /// Returns an indexed parallel iterator with the result of
+    /// [`Database::query()`] for each address, so that the results can be
+    /// collected in the order of the addresses.
+    ///
+    /// Unless the database is in memory, each thread of the pool reads rows
+    /// and strings into its own scratch buffer.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::net::IpAddr;
+    ///
+    /// use ip2proxy::{Columns, Database};
+    /// use rayon::prelude::*;
+    ///
+    /// let db = Database::open("data/IP2PROXY-IP-PROXYTYPE-COUNTRY-REGION-CITY-ISP.SAMPLE.BIN")?;
+    ///
+    /// let addrs: Vec<IpAddr> = vec!["1.0.0.1".parse()?, "2001:db8::1".parse()?];
+    /// let rows = db
+    ///     .par_query(addrs.par_iter().copied(), Columns::PROXY_TYPE)
+    ///     .collect::<Result<Vec<_>, _>>()?;
+    /// assert_eq!(rows[0].as_ref().and_then(|r| r.proxy_type.as_deref()), Some("DCH"));
+    /// # Ok::<_, Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn par_query<I>(
+        &self,
+        addrs: I,
+        query: Columns,
+    ) -> impl IndexedParallelIterator<Item = Result<Option<Row>, Error>>
+    where
+        I: IntoParallelIterator<Item = IpAddr>,
+        I::Iter: IndexedParallelIterator,
+    {
+        addrs
+            .into_par_iter()
+            .map(move |addr| self.query(addr, query))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rayon::iter::IntoParallelRefIterator as _;
+
+    use super::*;
+    use crate::tests::{SAMPLE, SAMPLE_COUNTRIES};
+
+    #[test]
+    fn test_par_query() {
+        let db = Database::open(SAMPLE).unwrap();
+
+        // Enough addresses to be split across threads, in an order that
+        // alternates between ranges.
+        let expected: Vec<_> = SAMPLE_COUNTRIES.iter().cycle().take(10_000).collect();
+        let addrs: Vec<IpAddr> = expected
+            .iter()
+            .map(|(addr, _)| addr.parse().unwrap())
+            .collect();
+
+        let rows = db.par_query(addrs.par_iter().copied(), Columns::COUNTRY_SHORT);
+        assert_eq!(rows.len(), addrs.len());
+        let rows = rows.collect::<Result<Vec<_>, _>>().unwrap();
+        for (row, (addr, country)) in rows.iter().zip(&expected) {
+            let row = row.as_ref().unwrap();
+            assert_eq!(row.country_short.as_deref(), Some(*country), "{addr}");
+            assert_eq!(row.proxy_type, None);
+        }
+
+        // Indexed, so it can be consumed from the back.
+        let last = db
+            .par_query(addrs.par_iter().copied(), Columns::COUNTRY_SHORT)
+            .rev()
+            .find_first(|_| true)
+            .unwrap()
+            .unwrap();
+        assert_eq!(last, rows.last().unwrap().clone());
+    }
+}
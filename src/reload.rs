@@ -0,0 +1,164 @@
+use std::{
+    net::IpAddr,
+    path::Path,
+    sync::{Arc, PoisonError, RwLock},
+};
+
+use positioned_io::RandomAccessFile;
+
+use crate::{Columns, Database, Error, Row, Source};
+
+/// An IP2Proxy BIN database that can be replaced at runtime, for example
+/// when a new release is published.
+///
+/// Lookups always see either the old or the new database. Lookups that are
+/// in progress while the database is replaced finish on the old database,
+/// which is closed once the last of them completes.
+///
+/// # Example
+///
+/// ```
+/// use ip2proxy::{Columns, ReloadableDatabase};
+///
+/// let db = ReloadableDatabase::open("data/IP2PROXY-IP-PROXYTYPE-COUNTRY-REGION-CITY-ISP.SAMPLE.BIN")?;
+///
+/// let row = db.query("1.0.0.1".parse()?, Columns::PROXY_TYPE)?;
+/// assert_eq!(row.and_then(|r| r.proxy_type), Some(String::from("DCH")));
+///
+/// // Later, when a new file is available.
+/// db.reload("data/IP2PROXY-IP-PROXYTYPE-COUNTRY-REGION-CITY-ISP.SAMPLE.BIN")?;
+/// # Ok::<_, Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+pub struct ReloadableDatabase<R = RandomAccessFile> {
+    current: RwLock<Arc<Database<R>>>,
+}
+
+impl ReloadableDatabase {
+    /// Open a database file.
+    ///
+    /// # Errors
+    ///
+    /// See [`Database::open()`].
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Database::open(path).map(ReloadableDatabase::new)
+    }
+
+    /// Open a new database file and swap it in, if it is compatible with
+    /// the current database. See [`ReloadableDatabase::replace()`].
+    ///
+    /// # Errors
+    ///
+    /// * Error while opening the file.
+    /// * Error while reading from the file.
+    /// * Invalid data in header section or index section.
+    /// * The new database is not compatible with the current database
+    ///   ([`Error::IncompatibleDatabase`]).
+    pub fn reload<P: AsRef<Path>>(&self, path: P) -> Result<Arc<Database>, Error> {
+        self.replace(Database::open(path)?)
+    }
+}
+
+impl<R> ReloadableDatabase<R> {
+    /// Wrap a database.
+    pub fn new(db: Database<R>) -> ReloadableDatabase<R> {
+        ReloadableDatabase {
+            current: RwLock::new(Arc::new(db)),
+        }
+    }
+
+    /// Get the current database. The returned handle remains valid even if
+    /// the database is replaced in the meantime.
+    pub fn load(&self) -> Arc<Database<R>> {
+        Arc::clone(&self.current.read().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// Swap in a new database. Returns the previous database.
+    ///
+    /// The new database must have the same package version and set of
+    /// columns as the current database.
+    ///
+    /// # Errors
+    ///
+    /// * The new database is not compatible with the current database
+    ///   ([`Error::IncompatibleDatabase`]).
+    pub fn replace(&self, db: Database<R>) -> Result<Arc<Database<R>>, Error> {
+        let mut current = self.current.write().unwrap_or_else(PoisonError::into_inner);
+        if db.package_version() != current.package_version() || db.columns() != current.columns() {
+            return Err(Error::IncompatibleDatabase {
+                expected_px: current.package_version(),
+                expected_columns: current.columns(),
+                px: db.package_version(),
+                columns: db.columns(),
+            });
+        }
+        Ok(std::mem::replace(&mut *current, Arc::new(db)))
+    }
+}
+
+impl<R: Source> ReloadableDatabase<R> {
+    /// Look up information for an IP address in the current database.
+    ///
+    /// See [`Database::query()`].
+    ///
+    /// # Errors
+    ///
+    /// * Error while reading from the source.
+    /// * Invalid row or string data.
+    pub fn query(&self, addr: IpAddr, query: Columns) -> Result<Option<Row>, Error> {
+        self.load().query(addr, query)
+    }
+}
+
+impl<R> From<Database<R>> for ReloadableDatabase<R> {
+    fn from(db: Database<R>) -> ReloadableDatabase<R> {
+        ReloadableDatabase::new(db)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DatabaseBuilder;
+
+    #[test]
+    fn test_replace_incompatible() {
+        let bytes =
+            std::fs::read("data/IP2PROXY-IP-PROXYTYPE-COUNTRY-REGION-CITY-ISP.SAMPLE.BIN").unwrap();
+        let db = ReloadableDatabase::new(Database::from_bytes(bytes.clone()).unwrap());
+        let addr = "1.0.0.1".parse().unwrap();
+        let expected = db.query(addr, Columns::all()).unwrap();
+        assert!(expected.is_some());
+
+        // Different package version.
+        let mut builder = DatabaseBuilder::new(1).unwrap();
+        builder
+            .push(
+                addr,
+                addr,
+                Row {
+                    proxy_type: Some(String::from("VPN")),
+                    ..Row::default()
+                },
+            )
+            .unwrap();
+        let other = builder.build().unwrap();
+        assert_ne!(other.package_version(), db.load().package_version());
+        assert!(matches!(
+            db.replace(other),
+            Err(Error::IncompatibleDatabase {
+                expected_px: 4,
+                px: 1,
+                ..
+            })
+        ));
+
+        // Old database stays loaded.
+        assert_eq!(db.query(addr, Columns::all()).unwrap(), expected);
+
+        // Compatible database is swapped in.
+        let prev = db.replace(Database::from_bytes(bytes).unwrap()).unwrap();
+        assert_eq!(prev.query(addr, Columns::all()).unwrap(), expected);
+        assert_eq!(db.query(addr, Columns::all()).unwrap(), expected);
+    }
+}
@@ -0,0 +1,258 @@
+use std::{fmt, io, net::IpAddr};
+
+use crate::{Columns, Database, Error, Row, Source, clamp_ip, col_ptr, normalize_ip, str_at};
+
+impl<R: Source + AsRef<[u8]>> Database<R> {
+    /// Look up an IP address in a database that is stored in memory, like
+    /// [`Database::from_bytes()`] or a memory-mapped file, without copying
+    /// any strings.
+    ///
+    /// Fields of the returned [`RowRef`] are decoded only when accessed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ip2proxy::Database;
+    ///
+    /// let bytes = std::fs::read("data/IP2PROXY-IP-PROXYTYPE-COUNTRY-REGION-CITY-ISP.SAMPLE.BIN")?;
+    /// let db = Database::from_bytes(bytes)?;
+    ///
+    /// let row = db.query_ref("1.0.0.1".parse()?)?.unwrap();
+    /// assert_eq!(row.proxy_type()?, Some("DCH"));
+    /// # Ok::<_, Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * Error while reading from the source.
+    /// * Invalid row data ([`Error::CorruptIndex`],
+    ///   [`Error::SearchOutOfBounds`]).
+    pub fn query_ref(&self, addr: IpAddr) -> Result<Option<RowRef<'_>>, Error> {
+        let Some((index, raw)) = self.search_row(clamp_ip(normalize_ip(addr)), 0)? else {
+            return Ok(None);
+        };
+        let table = self.table(raw.from.is_ipv6()).expect("table of found row");
+        let bytes = self.reader.as_ref();
+        let start = u64::from(table.base_ptr) - 1
+            + u64::from(index) * table.row_size as u64
+            + table.addr_size as u64;
+        let cols = usize::try_from(start)
+            .ok()
+            .and_then(|start| bytes.get(start..)?.get(..table.row_size - table.addr_size))
+            .ok_or(io::Error::from(io::ErrorKind::UnexpectedEof))?;
+        Ok(Some(RowRef {
+            bytes,
+            cols,
+            columns: self.header.columns,
+        }))
+    }
+}
+
+/// Borrowed database record for an IP address, with lazily decoded fields.
+///
+/// Use [`Database::query_ref()`] to obtain this from a database.
+#[derive(Copy, Clone)]
+pub struct RowRef<'db> {
+    bytes: &'db [u8],
+    cols: &'db [u8],
+    columns: Columns,
+}
+
+impl<'db> RowRef<'db> {
+    /// Decode the value of a single column.
+    ///
+    /// Returns `None` if the column is not supported by the database, or if
+    /// `column` is not exactly one column.
+    ///
+    /// # Errors
+    ///
+    /// * String out of bounds.
+    /// * String is not valid UTF-8 ([`Error::InvalidString`]).
+    pub fn get(&self, column: Columns) -> Result<Option<&'db str>, Error> {
+        if column.bits().count_ones() != 1 {
+            return Ok(None);
+        }
+        let Some(ptr) = col_ptr(self.columns, self.cols, column)? else {
+            return Ok(None);
+        };
+        str_at(self.bytes, ptr).map(Some)
+    }
+
+    /// Decode the value of [`Row::proxy_type`].
+    ///
+    /// # Errors
+    ///
+    /// See [`RowRef::get()`].
+    pub fn proxy_type(&self) -> Result<Option<&'db str>, Error> {
+        self.get(Columns::PROXY_TYPE)
+    }
+
+    /// Decode the value of [`Row::country_short`].
+    ///
+    /// # Errors
+    ///
+    /// See [`RowRef::get()`].
+    pub fn country_short(&self) -> Result<Option<&'db str>, Error> {
+        self.get(Columns::COUNTRY_SHORT)
+    }
+
+    /// Decode the value of [`Row::country_long`].
+    ///
+    /// # Errors
+    ///
+    /// See [`RowRef::get()`].
+    pub fn country_long(&self) -> Result<Option<&'db str>, Error> {
+        self.get(Columns::COUNTRY_LONG)
+    }
+
+    /// Decode the value of [`Row::region`].
+    ///
+    /// # Errors
+    ///
+    /// See [`RowRef::get()`].
+    pub fn region(&self) -> Result<Option<&'db str>, Error> {
+        self.get(Columns::REGION)
+    }
+
+    /// Decode the value of [`Row::city`].
+    ///
+    /// # Errors
+    ///
+    /// See [`RowRef::get()`].
+    pub fn city(&self) -> Result<Option<&'db str>, Error> {
+        self.get(Columns::CITY)
+    }
+
+    /// Decode the value of [`Row::isp`].
+    ///
+    /// # Errors
+    ///
+    /// See [`RowRef::get()`].
+    pub fn isp(&self) -> Result<Option<&'db str>, Error> {
+        self.get(Columns::ISP)
+    }
+
+    /// Decode the value of [`Row::domain`].
+    ///
+    /// # Errors
+    ///
+    /// See [`RowRef::get()`].
+    pub fn domain(&self) -> Result<Option<&'db str>, Error> {
+        self.get(Columns::DOMAIN)
+    }
+
+    /// Decode the value of [`Row::usage_type`].
+    ///
+    /// # Errors
+    ///
+    /// See [`RowRef::get()`].
+    pub fn usage_type(&self) -> Result<Option<&'db str>, Error> {
+        self.get(Columns::USAGE_TYPE)
+    }
+
+    /// Decode the value of [`Row::asn`].
+    ///
+    /// # Errors
+    ///
+    /// See [`RowRef::get()`].
+    pub fn asn(&self) -> Result<Option<&'db str>, Error> {
+        self.get(Columns::ASN)
+    }
+
+    /// Decode the value of [`Row::as_name`].
+    ///
+    /// # Errors
+    ///
+    /// See [`RowRef::get()`].
+    pub fn as_name(&self) -> Result<Option<&'db str>, Error> {
+        self.get(Columns::AS_NAME)
+    }
+
+    /// Decode the value of [`Row::last_seen`].
+    ///
+    /// # Errors
+    ///
+    /// See [`RowRef::get()`].
+    pub fn last_seen(&self) -> Result<Option<&'db str>, Error> {
+        self.get(Columns::LAST_SEEN)
+    }
+
+    /// Decode the value of [`Row::threat`].
+    ///
+    /// # Errors
+    ///
+    /// See [`RowRef::get()`].
+    pub fn threat(&self) -> Result<Option<&'db str>, Error> {
+        self.get(Columns::THREAT)
+    }
+
+    /// Decode the value of [`Row::provider`].
+    ///
+    /// # Errors
+    ///
+    /// See [`RowRef::get()`].
+    pub fn provider(&self) -> Result<Option<&'db str>, Error> {
+        self.get(Columns::PROVIDER)
+    }
+
+    /// Decode all supported columns into an owned [`Row`].
+    ///
+    /// # Errors
+    ///
+    /// See [`RowRef::get()`].
+    pub fn to_row(&self) -> Result<Row, Error> {
+        let mut row = Row::default();
+        for column in self.columns.iter() {
+            row.set(column, self.get(column)?.map(ToOwned::to_owned));
+        }
+        Ok(row)
+    }
+}
+
+impl fmt::Debug for RowRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(
+                self.columns
+                    .iter_names()
+                    .map(|(name, column)| (name, self.get(column))),
+            )
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::SAMPLE;
+
+    #[test]
+    fn test_query_ref() {
+        let bytes = std::fs::read(SAMPLE).unwrap();
+        let addr = "1.0.0.1".parse().unwrap();
+
+        // Strings are borrowed from the database.
+        let db = Database::from_bytes(bytes.clone()).unwrap();
+        let row_ref = db.query_ref(addr).unwrap().unwrap();
+        assert_eq!(
+            Some(row_ref.to_row().unwrap()),
+            db.query(addr, Columns::all()).unwrap()
+        );
+        let city = row_ref.city().unwrap().unwrap();
+        assert_eq!(city, "Brisbane");
+        assert!(db.reader.as_ptr_range().contains(&city.as_ptr()));
+        let offset = city.as_ptr() as usize - db.reader.as_ptr() as usize;
+
+        // Fields are decoded only when accessed.
+        let mut corrupt = bytes;
+        corrupt[offset] = 0xff;
+        let db = Database::from_bytes(corrupt).unwrap();
+        let row_ref = db.query_ref(addr).unwrap().unwrap();
+        assert_eq!(row_ref.proxy_type().unwrap(), Some("DCH"));
+        assert_eq!(row_ref.region().unwrap(), Some("Queensland"));
+        assert!(matches!(row_ref.city(), Err(Error::InvalidString { .. })));
+        assert!(row_ref.to_row().is_err());
+        assert!(db.query(addr, Columns::all()).is_err());
+        assert!(db.query(addr, Columns::PROXY_TYPE).is_ok());
+    }
+}
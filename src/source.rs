@@ -0,0 +1,62 @@
+use std::fs::File;
+
+use positioned_io::{RandomAccessFile, ReadAt, Slice};
+
+/// A storage backend of a [`Database`](crate::Database).
+///
+/// Backends that hold the whole database in memory return their bytes
+/// from [`Source::as_bytes()`], so that lookups read rows and strings
+/// directly from the slice. Other backends are read with positioned reads
+/// through [`ReadAt`].
+///
+/// # Example
+///
+/// A custom backend that is read through [`ReadAt`]:
+///
+/// ```
+/// use std::io;
+///
+/// use ip2proxy::{Source, positioned_io::ReadAt};
+///
+/// struct Storage(Vec<u8>);
+///
+/// impl ReadAt for Storage {
+///     fn read_at(&self, pos: u64, buf: &mut [u8]) -> io::Result<usize> {
+///         self.0.read_at(pos, buf)
+///     }
+/// }
+///
+/// impl Source for Storage {}
+/// ```
+pub trait Source: ReadAt {
+    /// Get the bytes of the source, if it is stored in memory.
+    ///
+    /// Defaults to `None`.
+    fn as_bytes(&self) -> Option<&[u8]> {
+        None
+    }
+}
+
+impl Source for RandomAccessFile {}
+
+impl Source for File {}
+
+impl<I: ReadAt> Source for Slice<I> {}
+
+impl Source for Vec<u8> {
+    fn as_bytes(&self) -> Option<&[u8]> {
+        Some(self)
+    }
+}
+
+impl Source for &[u8] {
+    fn as_bytes(&self) -> Option<&[u8]> {
+        Some(self)
+    }
+}
+
+impl<S: Source + ?Sized> Source for &S {
+    fn as_bytes(&self) -> Option<&[u8]> {
+        (**self).as_bytes()
+    }
+}
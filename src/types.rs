@@ -0,0 +1,389 @@
+use std::{convert::Infallible, error::Error, fmt, io, str, str::FromStr};
+
+use crate::Columns;
+
+macro_rules! codes {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $(
+                #[doc = $doc:literal]
+                $variant:ident => $code:literal,
+            )*
+        }
+    ) => {
+        $(#[$meta])*
+        #[non_exhaustive]
+        #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+        pub enum $name {
+            $(
+                #[doc = concat!("`", $code, "`: ", $doc)]
+                $variant,
+            )*
+            /// Any other code, as it appears in the database.
+            Unknown(String),
+        }
+
+        impl $name {
+            /// Get the code as it appears in the database.
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $($name::$variant => $code,)*
+                    $name::Unknown(code) => code,
+                }
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(code: &str) -> $name {
+                match code {
+                    $($code => $name::$variant,)*
+                    _ => $name::Unknown(code.to_owned()),
+                }
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = Infallible;
+
+            fn from_str(code: &str) -> Result<$name, Infallible> {
+                Ok($name::from(code))
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl serde::Serialize for $name {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(self.as_str())
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl<'de> serde::Deserialize<'de> for $name {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<$name, D::Error> {
+                let code = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+                Ok($name::from(&*code))
+            }
+        }
+    };
+}
+
+codes! {
+    /// Type of proxy. See
+    /// [`Row::parsed_proxy_type()`](crate::Row::parsed_proxy_type()).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ip2proxy::ProxyType;
+    ///
+    /// assert_eq!("TOR".parse(), Ok(ProxyType::Tor));
+    /// assert_eq!(ProxyType::from("XYZ"), ProxyType::Unknown(String::from("XYZ")));
+    /// ```
+    pub enum ProxyType {
+        /// Anonymizing VPN service
+        Vpn => "VPN",
+        /// Tor exit node
+        Tor => "TOR",
+        /// Data center, hosting provider, CDN
+        DataCenter => "DCH",
+        /// Public proxy
+        Public => "PUB",
+        /// Web based proxy
+        Web => "WEB",
+        /// Search engine spider
+        SearchEngineSpider => "SES",
+        /// Residential proxy
+        Residential => "RES",
+    }
+}
+
+codes! {
+    /// Usage type classification. See
+    /// [`Row::parsed_usage_types()`](crate::Row::parsed_usage_types()).
+    pub enum UsageType {
+        /// Commercial
+        Commercial => "COM",
+        /// Organization
+        Organization => "ORG",
+        /// Government
+        Government => "GOV",
+        /// Military
+        Military => "MIL",
+        /// University, college, school
+        Education => "EDU",
+        /// Library
+        Library => "LIB",
+        /// Content Delivery Network
+        ContentDelivery => "CDN",
+        /// Fixed Line ISP
+        FixedLineIsp => "ISP",
+        /// Mobile ISP
+        MobileIsp => "MOB",
+        /// Data center, hosting provider, transit
+        DataCenter => "DCH",
+        /// Search engine spider
+        SearchEngineSpider => "SES",
+        /// Reserved
+        Reserved => "RSV",
+    }
+}
+
+codes! {
+    /// Security threat. See
+    /// [`Row::parsed_threats()`](crate::Row::parsed_threats()).
+    pub enum Threat {
+        /// Email and forum spammers
+        Spam => "SPAM",
+        /// Network security scanners
+        Scanner => "SCANNER",
+        /// Malware infected devices
+        Botnet => "BOTNET",
+    }
+}
+
+/// Parses a field that is `-` if empty, and may hold multiple codes
+/// separated by `/`.
+pub(crate) fn parse_codes<'a, T: From<&'a str>>(field: Option<&'a str>) -> Vec<T> {
+    match field {
+        None | Some("-") | Some("") => Vec::new(),
+        Some(field) => field.split('/').map(T::from).collect(),
+    }
+}
+
+/// Parses a proxy type field that is `-` if the range is not a proxy.
+pub(crate) fn parse_proxy_type(field: Option<&str>) -> Option<ProxyType> {
+    match field {
+        None | Some("-") | Some("") => None,
+        Some(code) => Some(ProxyType::from(code)),
+    }
+}
+
+/// Checks if a row with the given country code and proxy type fields
+/// represents a known proxy of any kind.
+pub(crate) fn is_proxy(country_short: Option<&str>, proxy_type: Option<&str>) -> Option<bool> {
+    if country_short == Some("-") {
+        return Some(false);
+    }
+    proxy_type.map(|proxy_type| proxy_type != "-")
+}
+
+/// ISO 3166-1 alpha-2 country code, like `US`. See
+/// [`Row::parsed_country()`](crate::Row::parsed_country()).
+///
+/// # Example
+///
+/// ```
+/// use ip2proxy::CountryCode;
+///
+/// let code: CountryCode = "AU".parse()?;
+/// assert_eq!(code.as_str(), "AU");
+///
+/// assert!("Australia".parse::<CountryCode>().is_err());
+/// # Ok::<_, Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct CountryCode([u8; 2]);
+
+impl CountryCode {
+    /// Create a country code from two uppercase ASCII letters.
+    pub fn from_bytes(code: [u8; 2]) -> Option<CountryCode> {
+        if code.iter().all(u8::is_ascii_uppercase) {
+            Some(CountryCode(code))
+        } else {
+            None
+        }
+    }
+
+    /// Get the two uppercase ASCII letters of the country code.
+    pub fn to_bytes(self) -> [u8; 2] {
+        self.0
+    }
+
+    /// Get the country code as a string.
+    pub fn as_str(&self) -> &str {
+        str::from_utf8(&self.0).expect("ascii")
+    }
+}
+
+impl FromStr for CountryCode {
+    type Err = InvalidField;
+
+    fn from_str(code: &str) -> Result<CountryCode, InvalidField> {
+        code.as_bytes()
+            .try_into()
+            .ok()
+            .and_then(CountryCode::from_bytes)
+            .ok_or_else(|| InvalidField::new(Columns::COUNTRY_SHORT, code))
+    }
+}
+
+impl fmt::Display for CountryCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for CountryCode {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for CountryCode {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<CountryCode, D::Error> {
+        let code = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        code.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Parses a country code field that is `-` if empty.
+pub(crate) fn parse_country(field: Option<&str>) -> Result<Option<CountryCode>, InvalidField> {
+    match field {
+        None | Some("-") | Some("") => Ok(None),
+        Some(code) => code.parse().map(Some),
+    }
+}
+
+/// Error when a field holds a malformed value.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct InvalidField {
+    column: Columns,
+    value: String,
+}
+
+impl InvalidField {
+    pub(crate) fn new(column: Columns, value: &str) -> InvalidField {
+        InvalidField {
+            column,
+            value: value.to_owned(),
+        }
+    }
+
+    /// Get the column of the malformed field.
+    pub fn column(&self) -> Columns {
+        self.column
+    }
+
+    /// Get the malformed value.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+}
+
+impl fmt::Display for InvalidField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid value for ")?;
+        bitflags::parser::to_writer(&self.column, &mut *f)?;
+        write!(f, ": {:?}", self.value)
+    }
+}
+
+impl Error for InvalidField {}
+
+impl From<InvalidField> for io::Error {
+    fn from(err: InvalidField) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+/// Parses a numeric field that is `-` if empty.
+pub(crate) fn parse_number(
+    column: Columns,
+    field: Option<&str>,
+) -> Result<Option<u32>, InvalidField> {
+    match field {
+        None | Some("-") | Some("") => Ok(None),
+        Some(field) => field
+            .parse()
+            .map(Some)
+            .map_err(|_| InvalidField::new(column, field)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Row;
+
+    #[test]
+    fn test_codes() {
+        for (code, proxy_type) in [
+            ("VPN", ProxyType::Vpn),
+            ("DCH", ProxyType::DataCenter),
+            ("XYZ", ProxyType::Unknown(String::from("XYZ"))),
+        ] {
+            assert_eq!(code.parse(), Ok(proxy_type.clone()));
+            assert_eq!(proxy_type.to_string(), code);
+        }
+        assert_eq!("MOB".parse(), Ok(UsageType::MobileIsp));
+        assert_eq!(UsageType::MobileIsp.to_string(), "MOB");
+        assert_eq!("BOTNET".parse(), Ok(Threat::Botnet));
+        assert_eq!(Threat::Botnet.to_string(), "BOTNET");
+    }
+
+    #[test]
+    fn test_country_code() {
+        let code: CountryCode = "AU".parse().unwrap();
+        assert_eq!(code.to_bytes(), *b"AU");
+        assert_eq!(code.to_string(), "AU");
+        for invalid in ["", "-", "au", "AUS", "A"] {
+            let err = invalid.parse::<CountryCode>().unwrap_err();
+            assert_eq!(err.column(), Columns::COUNTRY_SHORT);
+            assert_eq!(err.value(), invalid);
+        }
+        assert_eq!(CountryCode::from_bytes(*b"a1"), None);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_serde_roundtrip() {
+        fn roundtrip<T>(value: T, json: &str)
+        where
+            T: serde::Serialize + serde::de::DeserializeOwned + fmt::Debug + PartialEq,
+        {
+            assert_eq!(serde_json::to_string(&value).unwrap(), json);
+            assert_eq!(serde_json::from_str::<T>(json).unwrap(), value);
+        }
+
+        roundtrip(ProxyType::Tor, r#""TOR""#);
+        roundtrip(ProxyType::Unknown(String::from("XYZ")), r#""XYZ""#);
+        roundtrip(UsageType::Education, r#""EDU""#);
+        roundtrip(Threat::Scanner, r#""SCANNER""#);
+        roundtrip("DE".parse::<CountryCode>().unwrap(), r#""DE""#);
+        assert!(serde_json::from_str::<CountryCode>(r#""Germany""#).is_err());
+    }
+
+    #[test]
+    fn test_empty_fields() {
+        for empty in ["", "-"] {
+            let row = Row {
+                proxy_type: Some(empty.to_owned()),
+                country_short: Some(empty.to_owned()),
+                usage_type: Some(empty.to_owned()),
+                asn: Some(empty.to_owned()),
+                last_seen: Some(empty.to_owned()),
+                threat: Some(empty.to_owned()),
+                ..Row::default()
+            };
+            assert_eq!(row.parsed_proxy_type(), None, "{empty:?}");
+            assert_eq!(row.parsed_country(), Ok(None), "{empty:?}");
+            assert_eq!(row.parsed_usage_types(), [], "{empty:?}");
+            assert_eq!(row.parsed_asn(), Ok(None), "{empty:?}");
+            assert_eq!(row.parsed_last_seen(), Ok(None), "{empty:?}");
+            assert_eq!(row.parsed_threats(), [], "{empty:?}");
+        }
+
+        let row = Row::default();
+        assert_eq!(row.parsed_proxy_type(), None);
+        assert_eq!(row.parsed_country(), Ok(None));
+    }
+}
@@ -0,0 +1,478 @@
+use std::{collections::HashSet, fmt, io, io::ErrorKind};
+
+use byteorder::{ByteOrder as _, LE};
+use positioned_io::{ReadBytesAtExt as _, Size};
+
+use crate::{COLUMN_LAYOUT, Columns, Database, Error, Family, IndexTable, Source, Table};
+
+/// Number of rows to read at once.
+const CHUNK_ROWS: usize = 4096;
+
+/// Problem found by [`Database::validate()`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum Problem {
+    /// The database date is not a valid date.
+    InvalidDate {
+        /// Year.
+        year: u8,
+        /// Month.
+        month: u8,
+        /// Day.
+        day: u8,
+    },
+    /// A table has rows but no index.
+    MissingIndex {
+        /// Table.
+        family: Family,
+    },
+    /// The file ends before the end of a table or index, as given by the
+    /// header.
+    Truncated {
+        /// Minimum length according to the header.
+        expected: u64,
+        /// Actual length of the file.
+        actual: u64,
+    },
+    /// An index entry points to rows outside of the table, or its first
+    /// row is more than one row after its last row. Entries with the first
+    /// row just after the last row are empty.
+    IndexOutOfBounds {
+        /// Table.
+        family: Family,
+        /// Index entry, for the upper 16 bits of the address.
+        bucket: u32,
+        /// First row.
+        low_row: u32,
+        /// Last row.
+        high_row: u32,
+    },
+    /// An index entry starts or ends before the previous entry.
+    IndexNotMonotonic {
+        /// Table.
+        family: Family,
+        /// Index entry, for the upper 16 bits of the address.
+        bucket: u32,
+    },
+    /// A row does not start after the previous row.
+    RowsNotIncreasing {
+        /// Table.
+        family: Family,
+        /// Row index.
+        row: u32,
+    },
+    /// A string pointer points beyond the end of the file.
+    StringOutOfBounds {
+        /// Table.
+        family: Family,
+        /// Row index.
+        row: u32,
+        /// Column of the pointer.
+        column: Columns,
+        /// Offset of the string.
+        ptr: u64,
+    },
+    /// A string is not valid UTF-8.
+    InvalidUtf8 {
+        /// Table.
+        family: Family,
+        /// Row index.
+        row: u32,
+        /// Column of the pointer.
+        column: Columns,
+        /// Offset of the string.
+        ptr: u64,
+    },
+}
+
+fn family_name(family: Family) -> &'static str {
+    match family {
+        Family::Ipv4 => "IPv4",
+        Family::Ipv6 => "IPv6",
+        Family::Both => "IPv4 and IPv6",
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Problem::InvalidDate { year, month, day } => {
+                write!(f, "invalid database date {year}.{month}.{day}")
+            }
+            Problem::MissingIndex { family } => {
+                write!(f, "{} table has no index", family_name(family))
+            }
+            Problem::Truncated { expected, actual } => write!(
+                f,
+                "file is truncated: expected at least {expected} bytes, got {actual}"
+            ),
+            Problem::IndexOutOfBounds {
+                family,
+                bucket,
+                low_row,
+                high_row,
+            } => write!(
+                f,
+                "{} index entry {bucket} has invalid rows {low_row} - {high_row}",
+                family_name(family)
+            ),
+            Problem::IndexNotMonotonic { family, bucket } => {
+                write!(
+                    f,
+                    "{} index entry {bucket} is not monotonic",
+                    family_name(family)
+                )
+            }
+            Problem::RowsNotIncreasing { family, row } => write!(
+                f,
+                "{} row {row} does not start after the previous row",
+                family_name(family)
+            ),
+            Problem::StringOutOfBounds {
+                family,
+                row,
+                column,
+                ptr,
+            } => {
+                write!(f, "{} row {row} column ", family_name(family))?;
+                bitflags::parser::to_writer(&column, &mut *f)?;
+                write!(f, " points beyond the end of the file ({ptr})")
+            }
+            Problem::InvalidUtf8 {
+                family,
+                row,
+                column,
+                ptr,
+            } => {
+                write!(f, "{} row {row} column ", family_name(family))?;
+                bitflags::parser::to_writer(&column, &mut *f)?;
+                write!(f, " points to invalid utf-8 data ({ptr})")
+            }
+        }
+    }
+}
+
+/// Result of [`Database::validate()`].
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct ValidationReport {
+    problems: Vec<Problem>,
+}
+
+impl ValidationReport {
+    /// Checks if no problems were found.
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    /// Get all problems, in the order they were found.
+    pub fn problems(&self) -> &[Problem] {
+        &self.problems
+    }
+}
+
+impl<R: Source + Size> Database<R> {
+    /// Check the structure of the entire database, to detect truncated or
+    /// corrupted files before they cause errors in queries.
+    ///
+    /// Checks that
+    ///
+    /// * the file is long enough for the tables and indexes given in the
+    ///   header,
+    /// * index entries are monotonic and point to rows within the table,
+    /// * row start addresses are strictly increasing, and
+    /// * every string pointer points to valid UTF-8 data within the file.
+    ///
+    /// Reads the entire file. Each distinct string is read only once.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ip2proxy::Database;
+    ///
+    /// let db = Database::open("data/IP2PROXY-IP-PROXYTYPE-COUNTRY-REGION-CITY-ISP.SAMPLE.BIN")?;
+    ///
+    /// let report = db.validate()?;
+    /// for problem in report.problems() {
+    ///     println!("{problem}");
+    /// }
+    /// assert!(report.is_ok());
+    /// # Ok::<_, Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors while reading from the source. Problems with the data are
+    /// instead collected in the [`ValidationReport`].
+    pub fn validate(&self) -> Result<ValidationReport, Error> {
+        let mut problems = Vec::new();
+        let size = self.reader.size()?;
+
+        let (year, month, day) = (self.year(), self.month(), self.day());
+        if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+            problems.push(Problem::InvalidDate { year, month, day });
+        }
+
+        // Tables and indexes, as given by the header.
+        let mut expected = crate::HEADER_LEN as u64;
+        for (family, table, index, index_ptr) in [
+            (
+                Family::Ipv4,
+                self.table(false),
+                &self.index_ipv4,
+                self.header.index_ptr_ipv4,
+            ),
+            (
+                Family::Ipv6,
+                self.table(true),
+                &self.index_ipv6,
+                self.header.index_ptr_ipv6,
+            ),
+        ] {
+            if index_ptr != 0 {
+                expected = expected.max(u64::from(index_ptr) - 1 + (8 << 16));
+            }
+            let Some(table) = table else {
+                continue;
+            };
+            expected = expected.max(table_end(&table));
+            match index {
+                Some(index) => validate_index(&mut problems, family, &table, index),
+                None if table.rows > 0 => problems.push(Problem::MissingIndex { family }),
+                None => (),
+            }
+        }
+        if let Some(actual) = size.filter(|actual| *actual < expected) {
+            problems.push(Problem::Truncated { expected, actual });
+        }
+
+        let mut checked = HashSet::new();
+        for (family, table) in [
+            (Family::Ipv4, self.table(false)),
+            (Family::Ipv6, self.table(true)),
+        ] {
+            if let Some(table) = table {
+                self.validate_rows(&mut problems, &mut checked, size, family, &table)?;
+            }
+        }
+
+        Ok(ValidationReport { problems })
+    }
+
+    fn validate_rows(
+        &self,
+        problems: &mut Vec<Problem>,
+        checked: &mut HashSet<u64>,
+        size: Option<u64>,
+        family: Family,
+        table: &Table,
+    ) -> Result<(), Error> {
+        // Only check rows that are within the file.
+        let rows = match size {
+            Some(size) => {
+                let available =
+                    size.saturating_sub(u64::from(table.base_ptr) - 1) / table.row_size as u64;
+                table.rows.min(u32::try_from(available).unwrap_or(u32::MAX))
+            }
+            None => table.rows,
+        };
+
+        let mut buf = vec![0; CHUNK_ROWS * table.row_size];
+        let mut prev: Option<u128> = None;
+        let mut row = 0;
+        while row < rows {
+            let chunk_rows = (rows - row).min(CHUNK_ROWS as u32);
+            let chunk = &mut buf[..chunk_rows as usize * table.row_size];
+            self.reader.read_exact_at(
+                u64::from(table.base_ptr) - 1 + u64::from(row) * table.row_size as u64,
+                chunk,
+            )?;
+
+            for raw in chunk.chunks_exact(table.row_size) {
+                let from = if table.addr_size == 4 {
+                    u128::from(LE::read_u32(raw))
+                } else {
+                    LE::read_u128(raw)
+                };
+                if prev.is_some_and(|prev| prev >= from) {
+                    problems.push(Problem::RowsNotIncreasing { family, row });
+                }
+                prev = Some(from);
+
+                let mut cols = &raw[table.addr_size..];
+                for col in COLUMN_LAYOUT {
+                    if !self.header.columns.intersects(col) {
+                        continue;
+                    }
+                    let ptr = u64::from(LE::read_u32(cols));
+                    cols = &cols[4..];
+                    for column in (col & self.header.columns).iter() {
+                        let ptr = if column == Columns::COUNTRY_LONG {
+                            ptr + 3
+                        } else {
+                            ptr
+                        };
+                        if !checked.insert(ptr) {
+                            continue;
+                        }
+                        match self.check_str(ptr, size)? {
+                            StrCheck::Ok => (),
+                            StrCheck::OutOfBounds => problems.push(Problem::StringOutOfBounds {
+                                family,
+                                row,
+                                column,
+                                ptr,
+                            }),
+                            StrCheck::InvalidUtf8 => problems.push(Problem::InvalidUtf8 {
+                                family,
+                                row,
+                                column,
+                                ptr,
+                            }),
+                        }
+                    }
+                }
+
+                row += 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_str(&self, ptr: u64, size: Option<u64>) -> io::Result<StrCheck> {
+        if size.is_some_and(|size| ptr >= size) {
+            return Ok(StrCheck::OutOfBounds);
+        }
+        let res = self.reader.read_u8_at(ptr).and_then(|len| {
+            if size.is_some_and(|size| ptr + 1 + u64::from(len) > size) {
+                return Err(ErrorKind::UnexpectedEof.into());
+            }
+            let mut buf = vec![0; usize::from(len)];
+            self.reader.read_exact_at(ptr + 1, &mut buf)?;
+            Ok(buf)
+        });
+        match res {
+            Ok(buf) if std::str::from_utf8(&buf).is_ok() => Ok(StrCheck::Ok),
+            Ok(_) => Ok(StrCheck::InvalidUtf8),
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => Ok(StrCheck::OutOfBounds),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+enum StrCheck {
+    Ok,
+    OutOfBounds,
+    InvalidUtf8,
+}
+
+fn table_end(table: &Table) -> u64 {
+    u64::from(table.base_ptr) - 1 + u64::from(table.rows) * table.row_size as u64
+}
+
+fn validate_index(problems: &mut Vec<Problem>, family: Family, table: &Table, index: &IndexTable) {
+    let mut prev = None;
+    for (bucket, range) in (0..).zip(&index.table) {
+        if range.high_row >= table.rows || range.low_row > range.high_row + 1 {
+            problems.push(Problem::IndexOutOfBounds {
+                family,
+                bucket,
+                low_row: range.low_row,
+                high_row: range.high_row,
+            });
+        }
+        if let Some((low_row, high_row)) = prev {
+            if range.low_row < low_row || range.high_row < high_row {
+                problems.push(Problem::IndexNotMonotonic { family, bucket });
+            }
+        }
+        prev = Some((range.low_row, range.high_row));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Vec<u8> {
+        std::fs::read("data/IP2PROXY-IP-PROXYTYPE-COUNTRY-REGION-CITY-ISP.SAMPLE.BIN").unwrap()
+    }
+
+    #[test]
+    fn test_validate_sample() {
+        let db = Database::from_bytes(sample()).unwrap();
+        assert_eq!(db.validate().unwrap(), ValidationReport::default());
+    }
+
+    #[test]
+    fn test_validate_built() {
+        let sample = Database::from_bytes(sample()).unwrap();
+        let mut builder = crate::DatabaseBuilder::new(4).unwrap();
+        builder.date(16, 11, 17);
+        for res in sample.iter(Columns::all()) {
+            let (start, end, row) = res.unwrap();
+            builder.push(start, end, row).unwrap();
+        }
+        assert!(builder.build().unwrap().validate().unwrap().is_ok());
+
+        let mut empty = crate::DatabaseBuilder::new(11).unwrap();
+        empty.date(24, 1, 1);
+        assert!(empty.build().unwrap().validate().unwrap().is_ok());
+    }
+
+    #[test]
+    fn test_validate_truncated() {
+        let mut buf = sample();
+        buf.truncate(buf.len() - 10);
+        let db = Database::from_bytes(buf).unwrap();
+        let report = db.validate().unwrap();
+        assert!(
+            report
+                .problems()
+                .iter()
+                .any(|problem| matches!(problem, Problem::StringOutOfBounds { .. }))
+        );
+    }
+
+    #[test]
+    fn test_validate_rows_not_increasing() {
+        let mut buf = sample();
+        let db = Database::from_bytes(buf.clone()).unwrap();
+        let table = db.table(false).unwrap();
+        let row_ptr = table.base_ptr as usize - 1 + table.row_size;
+        buf[row_ptr..row_ptr + 4].copy_from_slice(&[0; 4]);
+
+        let db = Database::from_bytes(buf).unwrap();
+        assert_eq!(
+            db.validate().unwrap().problems(),
+            &[Problem::RowsNotIncreasing {
+                family: Family::Ipv4,
+                row: 1
+            }]
+        );
+    }
+
+    #[test]
+    fn test_validate_index_bucket() {
+        // Empty bucket, as read by queries.
+        let db = Database::from_bytes(crate::tests::with_index_bucket(1, 0)).unwrap();
+        assert_eq!(db.validate().unwrap(), ValidationReport::default());
+
+        for (low_row, high_row) in [(2, 0), (0, 100)] {
+            let db =
+                Database::from_bytes(crate::tests::with_index_bucket(low_row, high_row)).unwrap();
+            assert!(
+                db.validate()
+                    .unwrap()
+                    .problems()
+                    .contains(&Problem::IndexOutOfBounds {
+                        family: Family::Ipv4,
+                        bucket: 10 << 8,
+                        low_row,
+                        high_row,
+                    }),
+                "{low_row} - {high_row}"
+            );
+        }
+    }
+}
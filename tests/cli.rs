@@ -0,0 +1,91 @@
+//! Integration tests for the `ip2proxy` command-line tool.
+
+use std::{
+    io::Write as _,
+    process::{Command, Output, Stdio},
+};
+
+const SAMPLE: &str = "data/IP2PROXY-IP-PROXYTYPE-COUNTRY-REGION-CITY-ISP.SAMPLE.BIN";
+
+fn ip2proxy(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_ip2proxy"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> &str {
+    std::str::from_utf8(&output.stdout).unwrap()
+}
+
+#[test]
+fn test_lookup() {
+    let output = ip2proxy(&["lookup", SAMPLE, "1.0.0.1", "2001:db8::1"], "");
+    assert!(output.status.success());
+    let lines: Vec<&str> = stdout(&output).lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("ip "));
+    assert!(lines[1].starts_with("1.0.0.1 "));
+    assert!(lines[1].contains(" DCH "));
+    assert!(lines[1].contains(" Brisbane "));
+    assert!(lines[2].starts_with("2001:db8::1 "));
+}
+
+#[test]
+fn test_lookup_json_stdin() {
+    let output = ip2proxy(
+        &[
+            "--format",
+            "json",
+            "lookup",
+            SAMPLE,
+            "--columns",
+            "proxy_type,country_short",
+        ],
+        "1.0.0.1\n::ffff:1.0.0.2\n",
+    );
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        concat!(
+            r#"{"ip":"1.0.0.1","proxy_type":"DCH","country_short":"AU"}"#,
+            "\n",
+            r#"{"ip":"::ffff:1.0.0.2","proxy_type":"DCH","country_short":"AU"}"#,
+            "\n",
+        )
+    );
+}
+
+#[test]
+fn test_verify() {
+    let output = ip2proxy(&["verify", SAMPLE], "");
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "");
+
+    let output = ip2proxy(&["--format", "json", "verify", SAMPLE], "");
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "{\"ok\":true,\"problems\":[]}\n");
+}
+
+#[test]
+fn test_verify_corrupt() {
+    // Invalid row range in the first IPv4 index entry.
+    let mut bytes = std::fs::read(SAMPLE).unwrap();
+    bytes[68..72].copy_from_slice(&0x7fff_ffff_u32.to_le_bytes());
+    let path = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("corrupt-index.bin");
+    std::fs::write(&path, bytes).unwrap();
+
+    let output = ip2proxy(&["verify", path.to_str().unwrap()], "");
+    assert!(!output.status.success());
+    assert!(stdout(&output).contains("IPv4 index entry 0 has invalid rows"));
+}